@@ -0,0 +1,266 @@
+// agents.rs - Several Pacmen on one board (multi-agent games)
+//
+// In a multi-agent game, every Pacman has its own lives, score and pending
+// moves, while the pellets and ghosts are shared. Pacman 0 lives in the usual
+// GameState fields; the others are kept in `pacmen`, and are swapped into
+// those fields whenever they act, so all of the single-Pacman logic (moves,
+// pellets, collisions, deaths) applies to them unchanged.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::*;
+use crate::direction::Direction;
+use crate::location::LocationState;
+use crate::moves::QueuedMove;
+use crate::state::GameState;
+use crate::tracking::Tracking;
+
+/// State of a Pacman other than Pacman 0
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct PacmanAgent {
+    pub loc: LocationState,
+    #[serde(skip, default = "empty_loc")]
+    pub prev_loc: LocationState,
+    pub score: u32,
+    pub lives: u8,
+    pub bonus_lives_awarded: u8,
+    pub buffered_turn: Direction,
+    #[serde(skip)]
+    pub buffered_turn_cells: u8,
+    #[serde(skip)]
+    pub move_queue: VecDeque<QueuedMove>,
+    #[serde(skip)]
+    pub speed_credit: u16,
+    pub tracking: Tracking,
+}
+
+impl Default for PacmanAgent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PacmanAgent {
+    pub fn new() -> Self {
+        Self {
+            loc: pacman_spawn_loc(),
+            prev_loc: empty_loc(),
+            score: 0,
+            lives: INIT_LIVES,
+            bonus_lives_awarded: 0,
+            buffered_turn: Direction::None,
+            buffered_turn_cells: 0,
+            move_queue: VecDeque::new(),
+            speed_credit: 0,
+            tracking: Tracking::default(),
+        }
+    }
+}
+
+/// Summary of one Pacman, for clients
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct PacmanSummary {
+    pub row: i8,
+    pub col: i8,
+    pub score: u32,
+    pub lives: u8,
+}
+
+impl GameState {
+    /// Number of Pacmen on the board (at least 1)
+    pub fn num_pacmen(&self) -> usize {
+        1 + self.pacmen.len()
+    }
+
+    // Add or remove Pacmen to match the rules (new ones start at the spawn)
+    pub(crate) fn sync_pacmen(&mut self) {
+        self.select_pacman(0);
+        let extra = (self.rules.pacmen as usize).saturating_sub(1);
+        self.pacmen.resize_with(extra, PacmanAgent::new);
+    }
+
+    // Exchange the state of Pacman 0 (in the usual fields) with another's
+    fn swap_pacman(&mut self, idx: usize) {
+        let agent = &mut self.pacmen[idx];
+        std::mem::swap(&mut self.pacman_loc, &mut agent.loc);
+        std::mem::swap(&mut self.pacman_prev_loc, &mut agent.prev_loc);
+        std::mem::swap(&mut self.curr_score, &mut agent.score);
+        std::mem::swap(&mut self.curr_lives, &mut agent.lives);
+        std::mem::swap(
+            &mut self.bonus_lives_awarded,
+            &mut agent.bonus_lives_awarded,
+        );
+        std::mem::swap(&mut self.buffered_turn, &mut agent.buffered_turn);
+        std::mem::swap(
+            &mut self.buffered_turn_cells,
+            &mut agent.buffered_turn_cells,
+        );
+        std::mem::swap(&mut self.move_queue, &mut agent.move_queue);
+        std::mem::swap(&mut self.pacman_speed_credit, &mut agent.speed_credit);
+        std::mem::swap(&mut self.tracking, &mut agent.tracking);
+    }
+
+    // Swap the given Pacman into the usual fields (swapping the last one out)
+    fn select_pacman(&mut self, pacman: u8) {
+        if pacman == self.curr_pacman {
+            return;
+        }
+        if self.curr_pacman > 0 {
+            self.swap_pacman(self.curr_pacman as usize - 1);
+        }
+        if pacman > 0 {
+            self.swap_pacman(pacman as usize - 1);
+        }
+        self.curr_pacman = pacman;
+    }
+
+    /// Run some game logic on behalf of one Pacman (None if there is no such
+    /// Pacman); anything it does to "Pacman" applies to that Pacman
+    pub fn with_pacman<T>(&mut self, pacman: usize, f: impl FnOnce(&mut Self) -> T) -> Option<T> {
+        if pacman >= self.num_pacmen() {
+            return None;
+        }
+        let last = self.curr_pacman;
+        self.select_pacman(pacman as u8);
+        let result = f(self);
+        self.select_pacman(last);
+        Some(result)
+    }
+
+    /// Run some game logic for each Pacman in turn
+    pub fn for_each_pacman(&mut self, mut f: impl FnMut(&mut Self)) {
+        for pacman in 0..self.num_pacmen() {
+            self.with_pacman(pacman, &mut f);
+        }
+    }
+
+    // Location of a Pacman, wherever its state is currently kept
+    fn pacman_loc_of(&self, pacman: usize) -> &LocationState {
+        let curr = self.curr_pacman as usize;
+        if pacman == curr {
+            &self.pacman_loc
+        } else if pacman == 0 {
+            &self.pacmen[curr - 1].loc
+        } else {
+            &self.pacmen[pacman - 1].loc
+        }
+    }
+
+    /// Summary of a Pacman (None if there is no such Pacman)
+    pub fn get_pacman_summary(&self, pacman: usize) -> Option<PacmanSummary> {
+        if pacman >= self.num_pacmen() {
+            return None;
+        }
+        let curr = self.curr_pacman as usize;
+        let (score, lives) = if pacman == curr {
+            (self.curr_score, self.curr_lives)
+        } else {
+            let agent = &self.pacmen[if pacman == 0 { curr - 1 } else { pacman - 1 }];
+            (agent.score, agent.lives)
+        };
+        let (row, col) = self.pacman_loc_of(pacman).get_coords();
+        Some(PacmanSummary {
+            row,
+            col,
+            score,
+            lives,
+        })
+    }
+
+    /// Whether every Pacman is out of lives
+    pub fn is_game_over(&self) -> bool {
+        self.curr_lives == 0 && self.pacmen.iter().all(|agent| agent.lives == 0)
+    }
+
+    /// The Pacman on the board closest to a cell (Pacman 0 if none are)
+    pub fn nearest_pacman(&self, row: i8, col: i8) -> usize {
+        (0..self.num_pacmen())
+            .filter(|&pacman| !self.pacman_loc_of(pacman).is_empty())
+            .min_by_key(|&pacman| {
+                let (pacman_row, pacman_col) = self.pacman_loc_of(pacman).get_coords();
+                self.dist_sq(row, col, pacman_row, pacman_col)
+            })
+            .unwrap_or(0)
+    }
+
+    /// Chase target of a ghost, aimed at the Pacman closest to it
+    pub fn get_nearest_chase_target(&mut self, idx: usize) -> (i8, i8) {
+        let color = self.ghosts[idx].color;
+        let (row, col) = self.ghosts[idx].get_pos();
+        let pacman = self.nearest_pacman(row, col);
+        self.with_pacman(pacman, |gs| gs.get_chase_target(color))
+            .unwrap_or_else(|| self.get_chase_target(color))
+    }
+
+    // Take the Pacmen not currently acting off the board (e.g. at the end of
+    // a level, which clears the acting Pacman itself)
+    pub(crate) fn clear_other_pacmen(&mut self) {
+        for agent in self.pacmen.iter_mut() {
+            agent.loc.copy_from(&empty_loc());
+            agent.prev_loc.copy_from(&empty_loc());
+            agent.buffered_turn = Direction::None;
+            agent.buffered_turn_cells = 0;
+            agent.move_queue.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::GameRules;
+
+    // A game with two Pacmen, both at the spawn (23, 13)
+    fn two_pacman_game() -> GameState {
+        let mut gs = GameState::with_rules(GameRules {
+            pacmen: 2,
+            ..Default::default()
+        });
+        gs.play();
+        assert_eq!(gs.num_pacmen(), 2);
+        gs
+    }
+
+    #[test]
+    fn pacmen_move_and_score_separately() {
+        let mut gs = two_pacman_game();
+        let before = gs.get_pacman_summary(0).unwrap();
+
+        gs.with_pacman(1, |gs| gs.move_pacman_dir(Direction::Left));
+        let second = gs.get_pacman_summary(1).unwrap();
+        assert_eq!((second.row, second.col), (23, 12));
+        assert!(second.score > 0);
+        assert_eq!(gs.get_pacman_summary(0), Some(before));
+        assert_eq!(gs.pacman_loc.get_coords(), (23, 13));
+        assert_eq!(gs.curr_score, 0);
+
+        // The pellets are shared, so Pacman 0 finds this one already eaten
+        gs.with_pacman(0, |gs| gs.move_pacman_dir(Direction::Left));
+        let first = gs.get_pacman_summary(0).unwrap();
+        assert_eq!((first.row, first.col), (23, 12));
+        assert_eq!(first.score, 0);
+        assert_eq!(gs.get_pacman_summary(1), Some(second));
+        assert_eq!(gs.get_pacman_summary(2), None);
+    }
+
+    #[test]
+    fn game_over_once_every_pacman_is_out() {
+        let mut gs = two_pacman_game();
+        gs.set_lives(0);
+        assert!(!gs.is_game_over());
+        assert_eq!(gs.with_pacman(1, |gs| gs.is_game_over()), Some(false));
+
+        gs.with_pacman(1, |gs| {
+            while gs.curr_lives > 1 {
+                gs.decrement_lives();
+            }
+        });
+        assert!(!gs.is_game_over());
+        gs.with_pacman(1, |gs| gs.decrement_lives());
+        assert_eq!(gs.get_pacman_summary(1).unwrap().lives, 0);
+        assert!(gs.is_game_over());
+        assert_eq!(gs.with_pacman(1, |gs| gs.is_game_over()), Some(true));
+    }
+}
@@ -0,0 +1,148 @@
+// clients.rs - Client identities and role-based command permissions
+//
+// The Go server only accepts commands from TrustedClientIPs; here, every
+// connection is given a role, and each role may only send certain commands.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::commands::{Command, CommandResponse};
+
+// Client id of commands from local callers (the Python bindings, or the
+// legacy command interface)
+pub const LOCAL_CLIENT: u32 = 0;
+
+/// What a connected client is allowed to do
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientRole {
+    /// Only watches the game
+    #[default]
+    Viewer,
+    /// Steers Pacman by direction
+    Bot,
+    /// Reports Pacman's tracked position
+    Cv,
+    /// Steers ghosts
+    Ghost,
+    /// Controls the match
+    Referee,
+}
+
+impl ClientRole {
+    /// Whether this role may send the given command
+    pub fn allows(&self, cmd: &Command) -> bool {
+        match self {
+            ClientRole::Referee => true,
+            ClientRole::Viewer => matches!(cmd, Command::RequestState),
+            ClientRole::Bot => matches!(
+                cmd,
+                Command::Move(_)
+                    | Command::QueueMove { .. }
+                    | Command::ClearMoves
+                    | Command::RequestState
+            ),
+            ClientRole::Cv => matches!(
+                cmd,
+                Command::MoveAbsolute { .. } | Command::Pose { .. } | Command::RequestState
+            ),
+            ClientRole::Ghost => matches!(
+                cmd,
+                Command::TakeGhost(_)
+                    | Command::ReleaseGhost(_)
+                    | Command::SteerGhost { .. }
+                    | Command::RequestState
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ClientRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ClientRole::Viewer => "viewer",
+            ClientRole::Bot => "bot",
+            ClientRole::Cv => "cv",
+            ClientRole::Ghost => "ghost",
+            ClientRole::Referee => "referee",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for ClientRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "viewer" => Ok(ClientRole::Viewer),
+            "bot" => Ok(ClientRole::Bot),
+            "cv" => Ok(ClientRole::Cv),
+            "ghost" => Ok(ClientRole::Ghost),
+            "referee" => Ok(ClientRole::Referee),
+            _ => Err(format!("unknown client role: {}", s)),
+        }
+    }
+}
+
+/// Identity of the client a command came from (and, in multi-agent games,
+/// the Pacman its commands control)
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ClientInfo {
+    pub id: u32,
+    pub role: ClientRole,
+    #[serde(default)]
+    pub pacman: u8,
+}
+
+impl ClientInfo {
+    pub fn new(id: u32, role: ClientRole) -> Self {
+        Self {
+            id,
+            role,
+            pacman: 0,
+        }
+    }
+
+    /// Direct this client's Pacman commands to another Pacman
+    pub fn with_pacman(mut self, pacman: u8) -> Self {
+        self.pacman = pacman;
+        self
+    }
+}
+
+/// A command message, tagged with the client that sent it (and where to
+/// send the response, if the connection wants one)
+#[derive(Clone, Debug)]
+pub struct ClientMessage {
+    pub client: ClientInfo,
+    pub msg: Vec<u8>,
+    pub reply_tx: Option<mpsc::Sender<CommandResponse>>,
+}
+
+impl ClientMessage {
+    pub fn new(client: ClientInfo, msg: Vec<u8>) -> Self {
+        Self {
+            client,
+            msg,
+            reply_tx: None,
+        }
+    }
+
+    /// Send responses to this command back on the given channel
+    pub fn with_reply(mut self, reply_tx: mpsc::Sender<CommandResponse>) -> Self {
+        self.reply_tx = Some(reply_tx);
+        self
+    }
+
+    /// Send a response back to the originating connection (a slow or closed
+    /// connection shouldn't stall the game loop, so the response may be dropped)
+    pub fn respond(&self, response: CommandResponse) {
+        if let Some(reply_tx) = &self.reply_tx {
+            let _ = reply_tx.try_send(response);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+// collision.rs - Collisions between robot footprints
+//
+// By default, Pacman and the ghosts are points on the grid, and only collide
+// on the same cell. On the physical field, the robots have a size, so two of
+// them can touch while still on neighboring cells (especially when Pacman's
+// sub-cell pose puts it near a cell boundary).
+
+use serde::{Deserialize, Serialize};
+
+use crate::ghost_state::GhostState;
+use crate::state::GameState;
+
+/// Footprint radii of the robots, in cells (a radius of 0.5 fills a cell)
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct Footprints {
+    pub pacman_radius: f32,
+    pub ghost_radius: f32,
+}
+
+impl Default for Footprints {
+    fn default() -> Self {
+        Self {
+            pacman_radius: 0.4,
+            ghost_radius: 0.4,
+        }
+    }
+}
+
+impl GameState {
+    /*
+    Pacman's position in cells: the last CV pose, if it still agrees with
+    Pacman's cell, or otherwise the center of the cell
+    */
+    pub fn pacman_position(&self) -> (f32, f32) {
+        let (row, col) = self.pacman_loc.get_coords();
+        let (row, col) = (row as f32, col as f32);
+        let limit = 0.5 + self.rules.pose_input.hysteresis.max(0.0);
+        match self.tracking.pose {
+            Some((pose_row, pose_col))
+                if (pose_row - row).abs() <= limit && (pose_col - col).abs() <= limit =>
+            {
+                (pose_row, pose_col)
+            }
+            _ => (row, col),
+        }
+    }
+
+    /// Check if Pacman's footprint overlaps a ghost's (footprints rule only)
+    pub(crate) fn footprints_overlap(&self, ghost: &GhostState) -> bool {
+        let footprints = match &self.rules.footprints {
+            Some(footprints) => footprints,
+            None => return false,
+        };
+        if self.pacman_loc.is_empty() || ghost.loc.row >= 32 || ghost.loc.col >= 32 {
+            return false;
+        }
+
+        // Ghosts are only tracked by cell, so they sit at the cell center
+        let (pacman_row, pacman_col) = self.pacman_position();
+        let (ghost_row, ghost_col) = ghost.get_pos();
+        let d_row = pacman_row - ghost_row as f32;
+        let d_col = pacman_col - ghost_col as f32;
+        let reach = footprints.pacman_radius + footprints.ghost_radius;
+        d_row * d_row + d_col * d_col < reach * reach
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::RED;
+    use crate::direction::Direction;
+    use crate::game_helpers::MoveOutcome;
+    use crate::location::LocationState;
+
+    // A playing game with Pacman at (23, 13), coming from (23, 12), and red
+    // at the given cell, coming from another
+    fn game_with_red(loc: (i8, i8), prev: (i8, i8)) -> GameState {
+        let mut gs = GameState::new();
+        gs.play();
+        gs.pacman_prev_loc.update_coords(23, 12);
+        let red = &mut gs.ghosts[RED as usize];
+        red.loc.update_coords(loc.0, loc.1);
+        red.prev_loc.update_coords(prev.0, prev.1);
+        gs
+    }
+
+    #[test]
+    fn swaps_are_only_opposite_moves() {
+        let loc = |col| LocationState::new(23, col, Direction::None);
+        assert!(loc(13).swapped_with(&loc(12), &loc(12), &loc(13)));
+        assert!(!loc(13).swapped_with(&loc(12), &loc(14), &loc(13)));
+        assert!(!loc(13).swapped_with(&loc(12), &loc(12), &loc(11)));
+    }
+
+    #[test]
+    fn pacman_dies_swapping_with_a_ghost() {
+        let mut gs = game_with_red((23, 12), (23, 13));
+        gs.check_collisions();
+        assert_eq!(gs.get_lives(), 2);
+        assert!(gs.pacman_loc.is_empty());
+
+        // Following the ghost is fine
+        let mut gs = game_with_red((23, 14), (23, 13));
+        gs.check_collisions();
+        assert_eq!(gs.get_lives(), 3);
+    }
+
+    #[test]
+    fn pacman_eats_a_frightened_ghost_it_swaps_with() {
+        let mut gs = game_with_red((23, 12), (23, 13));
+        gs.ghosts[RED as usize].set_fright_steps(10);
+        let score = gs.get_score();
+        gs.check_collisions();
+        assert_eq!(gs.get_lives(), 3);
+        assert!(gs.ghosts[RED as usize].is_eaten());
+        assert!(gs.get_score() > score);
+    }
+
+    // A game with footprints and red on the cell right of Pacman
+    fn footprints_game() -> GameState {
+        let mut gs = game_with_red((23, 14), (23, 14));
+        gs.rules.footprints = Some(Footprints::default());
+        gs
+    }
+
+    #[test]
+    fn neighbors_only_touch_with_footprints() {
+        let mut gs = footprints_game();
+        gs.tracking.pose = Some((23.0, 13.3));
+        assert!(gs.footprints_overlap(&gs.ghosts[RED as usize]));
+
+        gs.rules.footprints = None;
+        assert!(!gs.footprints_overlap(&gs.ghosts[RED as usize]));
+    }
+
+    #[test]
+    fn footprints_use_the_pose_within_the_cell() {
+        let mut gs = footprints_game();
+
+        // From the cell center, the footprints (0.8 cells together) don't reach
+        assert_eq!(gs.pacman_position(), (23.0, 13.0));
+        assert!(!gs.footprints_overlap(&gs.ghosts[RED as usize]));
+
+        // A pose that no longer agrees with Pacman's cell is ignored
+        gs.tracking.pose = Some((23.0, 14.9));
+        assert_eq!(gs.pacman_position(), (23.0, 13.0));
+        gs.tracking.pose = Some((23.0, 13.25));
+        assert_eq!(gs.pacman_position(), (23.0, 13.25));
+    }
+
+    #[test]
+    fn pacman_dies_leaning_into_a_ghost() {
+        let mut gs = footprints_game();
+        assert_eq!(gs.move_pacman_pose(23.0, 13.35, None), MoveOutcome::Moved);
+        assert_eq!(gs.get_lives(), 2);
+    }
+}
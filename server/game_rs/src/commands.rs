@@ -1,17 +1,674 @@
 // commands.rs - Command interpretation from byte messages
+//
+// Two encodings are accepted:
+// - Legacy single-byte commands (p, P, r, w/a/s/d, and x <row> <col>)
+// - Framed commands: FRAME_START, version, opcode, payload length, payload
+//   (multi-byte values are big-endian, like the serialized game state)
 
+use std::fmt;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
 
+use crate::clients::{ClientInfo, LOCAL_CLIENT};
+use crate::constants::{NUM_COLORS, REWIND_MAX_SECONDS};
 use crate::direction::Direction;
+use crate::events::GameEvent;
+use crate::game_helpers::MoveOutcome;
 use crate::logging::Logging;
 use crate::state::GameState;
 
-pub fn interpret_command(msg: &[u8], gs: &mut GameState, logger: &Logging) -> bool {
-    if msg.is_empty() {
-        return false;
+// First byte of a framed command (not used by any legacy command)
+pub const FRAME_START: u8 = b'#';
+
+// Version of the framed protocol understood by this server
+pub const PROTOCOL_VERSION: u8 = 1;
+
+// Length of the framed command header, before the payload
+const FRAME_HEADER_LEN: usize = 4;
+
+/****************************** Framed Opcodes ******************************/
+
+pub const OP_SET_LEVEL: u8 = 0x01;
+pub const OP_SET_LIVES: u8 = 0x02;
+pub const OP_SET_SEED: u8 = 0x03;
+pub const OP_STEP_TICKS: u8 = 0x04;
+pub const OP_REQUEST_STATE: u8 = 0x05;
+pub const OP_SET_SPEED: u8 = 0x06;
+pub const OP_QUEUE_MOVE: u8 = 0x07;
+pub const OP_CLEAR_MOVES: u8 = 0x08;
+pub const OP_POSE: u8 = 0x09;
+
+// Fixed-point scales of a pose: 1/256 cell, and 1/100 degree (or no heading)
+pub const POSE_CELL_SCALE: f32 = 256.0;
+pub const POSE_HEADING_SCALE: f32 = 100.0;
+pub const POSE_NO_HEADING: u16 = 0xffff;
+
+// Referee corrections
+pub const OP_GRANT_LIFE: u8 = 0x10;
+pub const OP_REMOVE_LIFE: u8 = 0x11;
+pub const OP_PLACE_PACMAN: u8 = 0x12;
+pub const OP_RESTORE_PELLET: u8 = 0x13;
+pub const OP_END_LEVEL: u8 = 0x14;
+pub const OP_REWIND: u8 = 0x15;
+
+// Ghost control
+pub const OP_TAKE_GHOST: u8 = 0x20;
+pub const OP_RELEASE_GHOST: u8 = 0x21;
+pub const OP_STEER_GHOST: u8 = 0x22;
+
+/// A parsed command
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    // Legacy commands
+    Pause,
+    Play,
+    Reset,
+    Move(Direction),
+    MoveAbsolute { row: i8, col: i8 },
+
+    // Framed commands
+    SetLevel(u8),
+    SetLives(u8),
+    SetSeed(u64),
+    StepTicks(u16),
+    RequestState,
+    /// Set the update period (ticks per game step)
+    SetSpeed(u8),
+    /// Move Pacman once the game reaches a tick
+    QueueMove { tick: u64, dir: Direction },
+    ClearMoves,
+    /// Sub-cell position and heading from the CV client (fixed-point)
+    Pose { row: u16, col: u16, heading: u16 },
+
+    // Referee corrections
+    GrantLife,
+    RemoveLife,
+    PlacePacman { row: i8, col: i8 },
+    RestorePellet { row: i8, col: i8 },
+    EndLevel,
+    /// Go back this many seconds
+    Rewind(u8),
+
+    // Ghost control
+    TakeGhost(u8),
+    ReleaseGhost(u8),
+    /// Direction for a controlled ghost to take at its next cell
+    SteerGhost { ghost: u8, dir: Direction },
+}
+
+/// What the caller needs to do after a command was applied to the state
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommandEffect {
+    /// Nothing, the command was fully applied
+    Applied,
+    /// Start a new game
+    Reset,
+    /// Run the game loop for this many ticks
+    StepTicks(u16),
+    /// Send the full game state
+    SendState,
+    /// Restore the state from this many seconds ago
+    Rewind(u8),
+    /// Nothing, a move was ignored because the game is paused
+    IgnoredPaused,
+    /// Nothing, a move was blocked by a wall
+    BlockedByWall,
+    /// A blocked move was kept as a buffered turn
+    Buffered,
+    /// Nothing yet, a far-away CV position needs confirming
+    Unconfirmed,
+}
+
+impl From<MoveOutcome> for CommandEffect {
+    fn from(outcome: MoveOutcome) -> Self {
+        match outcome {
+            MoveOutcome::Moved => CommandEffect::Applied,
+            MoveOutcome::Paused => CommandEffect::IgnoredPaused,
+            MoveOutcome::Blocked => CommandEffect::BlockedByWall,
+            MoveOutcome::Buffered => CommandEffect::Buffered,
+            MoveOutcome::Unconfirmed => CommandEffect::Unconfirmed,
+        }
+    }
+}
+
+/// Why a command could not be parsed or applied
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CommandError {
+    Empty,
+    UnknownCommand(u8),
+    BadLength { command: u8, expected: usize, actual: usize },
+    UnsupportedVersion(u8),
+    UnknownOpcode(u8),
+    InvalidValue { command: u8, value: u64 },
+    Forbidden { client: ClientInfo, command: &'static str },
+    QueueFull,
+    UnknownPacman(u8),
+    GhostControlled(u8),
+    NoDirection(&'static str),
+    NoHistory(u8),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Empty => write!(f, "empty command"),
+            CommandError::UnknownCommand(byte) => write!(f, "unknown command byte: {}", byte),
+            CommandError::BadLength {
+                command,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "invalid length for command {}: expected {} bytes, got {}",
+                command, expected, actual
+            ),
+            CommandError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported protocol version: {} (expected {})",
+                version, PROTOCOL_VERSION
+            ),
+            CommandError::UnknownOpcode(opcode) => write!(f, "unknown opcode: {}", opcode),
+            CommandError::InvalidValue { command, value } => {
+                write!(f, "invalid value for command {}: {}", command, value)
+            }
+            CommandError::Forbidden { client, command } => write!(
+                f,
+                "client {} ({}) may not send {}",
+                client.id, client.role, command
+            ),
+            CommandError::QueueFull => write!(f, "move queue is full"),
+            CommandError::UnknownPacman(pacman) => write!(f, "no such pacman: {}", pacman),
+            CommandError::GhostControlled(ghost) => {
+                write!(f, "another client controls ghost {}", ghost)
+            }
+            CommandError::NoDirection(command) => write!(f, "{} needs a direction", command),
+            CommandError::NoHistory(seconds) => {
+                write!(f, "no state recorded {} seconds ago", seconds)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+pub type CommandResult = Result<CommandEffect, CommandError>;
+
+/******************************* Responses *******************************/
+
+/// How a command was handled, as reported back to the client
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseStatus {
+    Applied,
+    IgnoredPaused,
+    BlockedByWall,
+    Buffered,
+    Unconfirmed,
+    Rejected,
+    Invalid,
+}
+
+impl ResponseStatus {
+    /// Status of a command that was accepted, by what it did
+    pub fn of(effect: &CommandEffect) -> Self {
+        match effect {
+            CommandEffect::IgnoredPaused => ResponseStatus::IgnoredPaused,
+            CommandEffect::BlockedByWall => ResponseStatus::BlockedByWall,
+            CommandEffect::Buffered => ResponseStatus::Buffered,
+            CommandEffect::Unconfirmed => ResponseStatus::Unconfirmed,
+            _ => ResponseStatus::Applied,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ResponseStatus::Applied => "applied",
+            ResponseStatus::IgnoredPaused => "ignored_paused",
+            ResponseStatus::BlockedByWall => "blocked_by_wall",
+            ResponseStatus::Buffered => "buffered",
+            ResponseStatus::Unconfirmed => "unconfirmed",
+            ResponseStatus::Rejected => "rejected",
+            ResponseStatus::Invalid => "invalid",
+        }
+    }
+}
+
+/// Response to a client's command, sent back to the connection it came from
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct CommandResponse {
+    pub client: u32,
+    pub tick: u64,
+    pub command: String,
+    pub status: ResponseStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl CommandResponse {
+    /// Describe the result of a command message from a client
+    pub fn new(client: u32, tick: u64, msg: &[u8], result: &CommandResult) -> Self {
+        let command = Command::parse(msg).map_or("unknown", |cmd| cmd.name());
+        let (status, error) = match result {
+            Ok(effect) => (ResponseStatus::of(effect), None),
+            Err(err @ CommandError::Forbidden { .. }) => {
+                (ResponseStatus::Rejected, Some(err.to_string()))
+            }
+            Err(err) => (ResponseStatus::Invalid, Some(err.to_string())),
+        };
+        Self {
+            client,
+            tick,
+            command: command.to_string(),
+            status,
+            error,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/******************************* Parsing *******************************/
+
+// Check that a message has exactly the expected length
+fn expect_len(command: u8, msg: &[u8], expected: usize) -> Result<(), CommandError> {
+    if msg.len() != expected {
+        return Err(CommandError::BadLength {
+            command,
+            expected,
+            actual: msg.len(),
+        });
+    }
+    Ok(())
+}
+
+// Check that a ghost color is valid
+fn parse_ghost(command: u8, ghost: u8) -> Result<u8, CommandError> {
+    if ghost as usize >= NUM_COLORS {
+        return Err(CommandError::InvalidValue {
+            command,
+            value: ghost as u64,
+        });
+    }
+    Ok(ghost)
+}
+
+impl Command {
+    /// Parse a command, in either the legacy or the framed encoding
+    pub fn parse(msg: &[u8]) -> Result<Command, CommandError> {
+        let Some(&first) = msg.first() else {
+            return Err(CommandError::Empty);
+        };
+
+        match first {
+            b'p' => Ok(Command::Pause),
+            b'P' => Ok(Command::Play),
+            b'r' | b'R' => Ok(Command::Reset),
+            b'w' => Ok(Command::Move(Direction::Up)),
+            b'a' => Ok(Command::Move(Direction::Left)),
+            b's' => Ok(Command::Move(Direction::Down)),
+            b'd' => Ok(Command::Move(Direction::Right)),
+            b'x' => {
+                expect_len(first, msg, 3)?;
+                Ok(Command::MoveAbsolute {
+                    row: msg[1] as i8,
+                    col: msg[2] as i8,
+                })
+            }
+            FRAME_START => Command::parse_frame(msg),
+            _ => Err(CommandError::UnknownCommand(first)),
+        }
     }
 
-    if logger.get_command_log_enabled() {
+    // Parse a framed command (starting with FRAME_START)
+    fn parse_frame(msg: &[u8]) -> Result<Command, CommandError> {
+        if msg.len() < FRAME_HEADER_LEN {
+            return Err(CommandError::BadLength {
+                command: FRAME_START,
+                expected: FRAME_HEADER_LEN,
+                actual: msg.len(),
+            });
+        }
+
+        let (version, opcode, len) = (msg[1], msg[2], msg[3] as usize);
+        if version != PROTOCOL_VERSION {
+            return Err(CommandError::UnsupportedVersion(version));
+        }
+        expect_len(opcode, &msg[FRAME_HEADER_LEN..], len)?;
+        let payload = &msg[FRAME_HEADER_LEN..];
+
+        match opcode {
+            OP_SET_LEVEL => {
+                expect_len(opcode, payload, 1)?;
+                Ok(Command::SetLevel(payload[0]))
+            }
+            OP_SET_LIVES => {
+                expect_len(opcode, payload, 1)?;
+                Ok(Command::SetLives(payload[0]))
+            }
+            OP_SET_SEED => {
+                expect_len(opcode, payload, 8)?;
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(payload);
+                Ok(Command::SetSeed(u64::from_be_bytes(bytes)))
+            }
+            OP_STEP_TICKS => {
+                expect_len(opcode, payload, 2)?;
+                Ok(Command::StepTicks(u16::from_be_bytes([payload[0], payload[1]])))
+            }
+            OP_REQUEST_STATE => {
+                expect_len(opcode, payload, 0)?;
+                Ok(Command::RequestState)
+            }
+            OP_SET_SPEED => {
+                expect_len(opcode, payload, 1)?;
+                Ok(Command::SetSpeed(payload[0]))
+            }
+            OP_QUEUE_MOVE => {
+                expect_len(opcode, payload, 9)?;
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(&payload[..8]);
+                let dir = Direction::from_index(payload[8]);
+                if dir == Direction::None {
+                    return Err(CommandError::InvalidValue {
+                        command: opcode,
+                        value: payload[8] as u64,
+                    });
+                }
+                Ok(Command::QueueMove {
+                    tick: u64::from_be_bytes(bytes),
+                    dir,
+                })
+            }
+            OP_CLEAR_MOVES => {
+                expect_len(opcode, payload, 0)?;
+                Ok(Command::ClearMoves)
+            }
+            OP_POSE => {
+                expect_len(opcode, payload, 6)?;
+                Ok(Command::Pose {
+                    row: u16::from_be_bytes([payload[0], payload[1]]),
+                    col: u16::from_be_bytes([payload[2], payload[3]]),
+                    heading: u16::from_be_bytes([payload[4], payload[5]]),
+                })
+            }
+            OP_GRANT_LIFE => {
+                expect_len(opcode, payload, 0)?;
+                Ok(Command::GrantLife)
+            }
+            OP_REMOVE_LIFE => {
+                expect_len(opcode, payload, 0)?;
+                Ok(Command::RemoveLife)
+            }
+            OP_PLACE_PACMAN => {
+                expect_len(opcode, payload, 2)?;
+                Ok(Command::PlacePacman {
+                    row: payload[0] as i8,
+                    col: payload[1] as i8,
+                })
+            }
+            OP_RESTORE_PELLET => {
+                expect_len(opcode, payload, 2)?;
+                Ok(Command::RestorePellet {
+                    row: payload[0] as i8,
+                    col: payload[1] as i8,
+                })
+            }
+            OP_END_LEVEL => {
+                expect_len(opcode, payload, 0)?;
+                Ok(Command::EndLevel)
+            }
+            OP_REWIND => {
+                expect_len(opcode, payload, 1)?;
+                Ok(Command::Rewind(payload[0]))
+            }
+            OP_TAKE_GHOST | OP_RELEASE_GHOST => {
+                expect_len(opcode, payload, 1)?;
+                let ghost = parse_ghost(opcode, payload[0])?;
+                if opcode == OP_TAKE_GHOST {
+                    Ok(Command::TakeGhost(ghost))
+                } else {
+                    Ok(Command::ReleaseGhost(ghost))
+                }
+            }
+            OP_STEER_GHOST => {
+                expect_len(opcode, payload, 2)?;
+                let ghost = parse_ghost(opcode, payload[0])?;
+                let dir = Direction::from_index(payload[1]);
+                if dir == Direction::None {
+                    return Err(CommandError::InvalidValue {
+                        command: opcode,
+                        value: payload[1] as u64,
+                    });
+                }
+                Ok(Command::SteerGhost { ghost, dir })
+            }
+            _ => Err(CommandError::UnknownOpcode(opcode)),
+        }
+    }
+
+    /// Short name of the command, for logs and rejection reports
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Pause => "pause",
+            Command::Play => "play",
+            Command::Reset => "reset",
+            Command::Move(_) => "move",
+            Command::MoveAbsolute { .. } => "move_absolute",
+            Command::SetLevel(_) => "set_level",
+            Command::SetLives(_) => "set_lives",
+            Command::SetSeed(_) => "set_seed",
+            Command::StepTicks(_) => "step_ticks",
+            Command::RequestState => "request_state",
+            Command::SetSpeed(_) => "set_speed",
+            Command::QueueMove { .. } => "queue_move",
+            Command::ClearMoves => "clear_moves",
+            Command::Pose { .. } => "pose",
+            Command::GrantLife => "grant_life",
+            Command::RemoveLife => "remove_life",
+            Command::PlacePacman { .. } => "place_pacman",
+            Command::RestorePellet { .. } => "restore_pellet",
+            Command::EndLevel => "end_level",
+            Command::Rewind(_) => "rewind",
+            Command::TakeGhost(_) => "take_ghost",
+            Command::ReleaseGhost(_) => "release_ghost",
+            Command::SteerGhost { .. } => "steer_ghost",
+        }
+    }
+
+    /// Whether the command acts on a particular Pacman (so in multi-agent
+    /// games, on the Pacman of the client sending it)
+    pub fn targets_pacman(&self) -> bool {
+        matches!(
+            self,
+            Command::Move(_)
+                | Command::MoveAbsolute { .. }
+                | Command::SetLives(_)
+                | Command::QueueMove { .. }
+                | Command::ClearMoves
+                | Command::Pose { .. }
+                | Command::GrantLife
+                | Command::RemoveLife
+                | Command::PlacePacman { .. }
+        )
+    }
+
+    /// Encode the command (the inverse of parse); commands with no direction
+    /// to move in have no encoding
+    pub fn encode(&self) -> Result<Vec<u8>, CommandError> {
+        let frame = |opcode: u8, payload: &[u8]| {
+            let mut buf = vec![FRAME_START, PROTOCOL_VERSION, opcode, payload.len() as u8];
+            buf.extend_from_slice(payload);
+            buf
+        };
+        let dir = match *self {
+            Command::Move(dir) | Command::QueueMove { dir, .. } => Some(dir),
+            Command::SteerGhost { dir, .. } => Some(dir),
+            _ => None,
+        };
+        if dir == Some(Direction::None) {
+            return Err(CommandError::NoDirection(self.name()));
+        }
+
+        let msg = match *self {
+            Command::Pause => vec![b'p'],
+            Command::Play => vec![b'P'],
+            Command::Reset => vec![b'r'],
+            Command::Move(dir) => match dir {
+                Direction::Up => vec![b'w'],
+                Direction::Left => vec![b'a'],
+                Direction::Down => vec![b's'],
+                _ => vec![b'd'],
+            },
+            Command::MoveAbsolute { row, col } => vec![b'x', row as u8, col as u8],
+            Command::SetLevel(level) => frame(OP_SET_LEVEL, &[level]),
+            Command::SetLives(lives) => frame(OP_SET_LIVES, &[lives]),
+            Command::SetSeed(seed) => frame(OP_SET_SEED, &seed.to_be_bytes()),
+            Command::StepTicks(ticks) => frame(OP_STEP_TICKS, &ticks.to_be_bytes()),
+            Command::RequestState => frame(OP_REQUEST_STATE, &[]),
+            Command::SetSpeed(period) => frame(OP_SET_SPEED, &[period]),
+            Command::QueueMove { tick, dir } => {
+                let mut payload = tick.to_be_bytes().to_vec();
+                payload.push(dir.to_index());
+                frame(OP_QUEUE_MOVE, &payload)
+            }
+            Command::ClearMoves => frame(OP_CLEAR_MOVES, &[]),
+            Command::Pose { row, col, heading } => {
+                let mut payload = row.to_be_bytes().to_vec();
+                payload.extend_from_slice(&col.to_be_bytes());
+                payload.extend_from_slice(&heading.to_be_bytes());
+                frame(OP_POSE, &payload)
+            }
+            Command::GrantLife => frame(OP_GRANT_LIFE, &[]),
+            Command::RemoveLife => frame(OP_REMOVE_LIFE, &[]),
+            Command::PlacePacman { row, col } => frame(OP_PLACE_PACMAN, &[row as u8, col as u8]),
+            Command::RestorePellet { row, col } => {
+                frame(OP_RESTORE_PELLET, &[row as u8, col as u8])
+            }
+            Command::EndLevel => frame(OP_END_LEVEL, &[]),
+            Command::Rewind(seconds) => frame(OP_REWIND, &[seconds]),
+            Command::TakeGhost(ghost) => frame(OP_TAKE_GHOST, &[ghost]),
+            Command::ReleaseGhost(ghost) => frame(OP_RELEASE_GHOST, &[ghost]),
+            Command::SteerGhost { ghost, dir } => frame(OP_STEER_GHOST, &[ghost, dir.to_index()]),
+        };
+        Ok(msg)
+    }
+
+    /****************************** Application ******************************/
+
+    /// Apply the command to the game state
+    pub fn apply(&self, gs: &mut GameState) -> CommandResult {
+        self.apply_as(LOCAL_CLIENT, gs)
+    }
+
+    /// Apply the command to the game state, on behalf of a client
+    pub fn apply_as(&self, client: u32, gs: &mut GameState) -> CommandResult {
+        match *self {
+            Command::Pause => gs.pause(),
+            Command::Play => gs.play(),
+            Command::Reset => return Ok(CommandEffect::Reset),
+            Command::Move(dir) => return Ok(gs.move_pacman_dir(dir).into()),
+            Command::MoveAbsolute { row, col } => {
+                return Ok(gs.move_pacman_absolute(row, col).into())
+            }
+            Command::SetLevel(level) => {
+                if level == 0 {
+                    return Err(CommandError::InvalidValue {
+                        command: OP_SET_LEVEL,
+                        value: 0,
+                    });
+                }
+                gs.set_level(level);
+            }
+            Command::SetLives(lives) => gs.set_lives(lives),
+            Command::SetSeed(seed) => {
+                info!("GAME: RNG seeded ({}) (t = {})", seed, gs.curr_ticks);
+                gs.rng = StdRng::seed_from_u64(seed);
+            }
+            Command::StepTicks(ticks) => return Ok(CommandEffect::StepTicks(ticks)),
+            Command::RequestState => return Ok(CommandEffect::SendState),
+            Command::SetSpeed(period) => {
+                if period == 0 {
+                    return Err(CommandError::InvalidValue {
+                        command: OP_SET_SPEED,
+                        value: 0,
+                    });
+                }
+                gs.set_update_period(period);
+            }
+            Command::QueueMove { tick, dir } => {
+                if !gs.queue_move(tick, dir) {
+                    return Err(CommandError::QueueFull);
+                }
+            }
+            Command::ClearMoves => gs.clear_moves(),
+            Command::Pose { row, col, heading } => {
+                let heading = (heading != POSE_NO_HEADING)
+                    .then(|| heading as f32 / POSE_HEADING_SCALE);
+                let (row, col) = (row as f32 / POSE_CELL_SCALE, col as f32 / POSE_CELL_SCALE);
+                return Ok(gs.move_pacman_pose(row, col, heading).into());
+            }
+            Command::GrantLife => gs.grant_life(),
+            Command::RemoveLife => gs.remove_life(),
+            Command::PlacePacman { row, col } => {
+                if !gs.place_pacman(row, col) {
+                    return Err(CommandError::InvalidValue {
+                        command: OP_PLACE_PACMAN,
+                        value: ((row as u8 as u64) << 8) | col as u8 as u64,
+                    });
+                }
+            }
+            Command::RestorePellet { row, col } => {
+                if !gs.restore_pellet(row, col) {
+                    return Err(CommandError::InvalidValue {
+                        command: OP_RESTORE_PELLET,
+                        value: ((row as u8 as u64) << 8) | col as u8 as u64,
+                    });
+                }
+            }
+            Command::EndLevel => gs.end_level(),
+            Command::Rewind(seconds) => {
+                if seconds == 0 || seconds > REWIND_MAX_SECONDS {
+                    return Err(CommandError::InvalidValue {
+                        command: OP_REWIND,
+                        value: seconds as u64,
+                    });
+                }
+                return Ok(CommandEffect::Rewind(seconds));
+            }
+            Command::TakeGhost(ghost) => {
+                if !gs.take_ghost_control(ghost as usize, client) {
+                    return Err(CommandError::GhostControlled(ghost));
+                }
+            }
+            Command::ReleaseGhost(ghost) => {
+                if !gs.release_ghost_control(ghost as usize, client) {
+                    return Err(CommandError::GhostControlled(ghost));
+                }
+            }
+            Command::SteerGhost { ghost, dir } => {
+                return gs
+                    .steer_ghost(ghost as usize, client, dir)
+                    .map(CommandEffect::from)
+                    .ok_or(CommandError::GhostControlled(ghost));
+            }
+        }
+        Ok(CommandEffect::Applied)
+    }
+}
+
+/// Parse and apply a command message, logging it if command logging is enabled
+pub fn execute_command(msg: &[u8], gs: &mut GameState, logger: &Logging) -> CommandResult {
+    execute_command_as(msg, LOCAL_CLIENT, gs, logger)
+}
+
+// Parse and apply a command message on behalf of a client
+fn execute_command_as(msg: &[u8], client: u32, gs: &mut GameState, logger: &Logging) -> CommandResult {
+    if logger.get_command_log_enabled() && !msg.is_empty() {
         let cmd_char = msg[0] as char;
         if msg.len() > 1 {
             info!("COMM: {} {:?}", cmd_char, &msg[1..]);
@@ -20,29 +677,276 @@ pub fn interpret_command(msg: &[u8], gs: &mut GameState, logger: &Logging) -> bo
         }
     }
 
-    match msg[0] {
-        b'p' => {
-            gs.pause();
+    Command::parse(msg)?.apply_as(client, gs)
+}
+
+/// Parse and apply a command from a client, if its role allows the command
+/// (rejected commands are reported as a game event)
+pub fn execute_client_command(
+    msg: &[u8],
+    client: &ClientInfo,
+    gs: &mut GameState,
+    logger: &Logging,
+) -> CommandResult {
+    let cmd = Command::parse(msg)?;
+    if !client.role.allows(&cmd) {
+        warn!(
+            "ERR: Client {} ({}) may not send {} (t = {})",
+            client.id,
+            client.role,
+            cmd.name(),
+            gs.curr_ticks
+        );
+        gs.emit(GameEvent::CommandRejected {
+            client: client.id,
+            role: client.role,
+            command: cmd.name().to_string(),
+        });
+        return Err(CommandError::Forbidden {
+            client: client.clone(),
+            command: cmd.name(),
+        });
+    }
+
+    // In multi-agent games, act on the client's own Pacman
+    if client.pacman == 0 || !cmd.targets_pacman() {
+        return execute_command_as(msg, client.id, gs, logger);
+    }
+    gs.with_pacman(client.pacman as usize, |gs| {
+        execute_command_as(msg, client.id, gs, logger)
+    })
+    .unwrap_or(Err(CommandError::UnknownPacman(client.pacman)))
+}
+
+/// Legacy interface: apply a command, returning true if the game should reset
+/// (the game loop is stepped here, and errors are only logged)
+pub fn interpret_command(msg: &[u8], gs: &mut GameState, logger: &Logging) -> bool {
+    match execute_command(msg, gs, logger) {
+        Ok(CommandEffect::Reset) => true,
+        Ok(CommandEffect::StepTicks(ticks)) => {
+            gs.step_ticks(ticks);
+            false
         }
-        b'P' => {
-            gs.play();
+        Ok(CommandEffect::Rewind(_)) => {
+            warn!("ERR: No state history to rewind. Ignoring...");
+            false
         }
-        b'r' | b'R' => return true,
-        b'w' => gs.move_pacman_dir(Direction::Up),
-        b'a' => gs.move_pacman_dir(Direction::Left),
-        b's' => gs.move_pacman_dir(Direction::Down),
-        b'd' => gs.move_pacman_dir(Direction::Right),
-        b'x' => {
-            if msg.len() != 3 {
-                warn!("ERR: Invalid position update (type 'x'). Ignoring...");
-                return false;
-            }
-            gs.move_pacman_absolute(msg[1] as i8, msg[2] as i8);
+        Ok(_) => false,
+        Err(CommandError::Empty) => false,
+        Err(err) => {
+            warn!("ERR: Invalid command ({}). Ignoring...", err);
+            false
         }
-        _ => {
-            warn!("Received unknown command byte: {}", msg[0]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One command of each kind, with non-trivial payloads
+    fn all_commands() -> Vec<Command> {
+        vec![
+            Command::Pause,
+            Command::Play,
+            Command::Reset,
+            Command::Move(Direction::Up),
+            Command::Move(Direction::Left),
+            Command::Move(Direction::Down),
+            Command::Move(Direction::Right),
+            Command::MoveAbsolute { row: 5, col: 26 },
+            Command::SetLevel(3),
+            Command::SetLives(2),
+            Command::SetSeed(0x0123_4567_89ab_cdef),
+            Command::StepTicks(0x1234),
+            Command::RequestState,
+            Command::SetSpeed(8),
+            Command::QueueMove {
+                tick: 0x1_0000_0001,
+                dir: Direction::Down,
+            },
+            Command::ClearMoves,
+            Command::Pose {
+                row: 23 * 256 + 128,
+                col: 13 * 256,
+                heading: 27000,
+            },
+            Command::GrantLife,
+            Command::RemoveLife,
+            Command::PlacePacman { row: 23, col: 13 },
+            Command::RestorePellet { row: 1, col: 1 },
+            Command::EndLevel,
+            Command::Rewind(3),
+            Command::TakeGhost(1),
+            Command::ReleaseGhost(2),
+            Command::SteerGhost {
+                ghost: 3,
+                dir: Direction::Left,
+            },
+        ]
+    }
+
+    // Frame a raw payload under an opcode
+    fn frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mut msg = vec![FRAME_START, PROTOCOL_VERSION, opcode, payload.len() as u8];
+        msg.extend_from_slice(payload);
+        msg
+    }
+
+    #[test]
+    fn encode_parse_round_trip() {
+        for command in all_commands() {
+            assert_eq!(
+                Command::parse(&command.encode().unwrap()),
+                Ok(command),
+                "{:?}",
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn commands_without_a_direction_do_not_encode() {
+        let commands = [
+            Command::Move(Direction::None),
+            Command::QueueMove {
+                tick: 10,
+                dir: Direction::None,
+            },
+            Command::SteerGhost {
+                ghost: 0,
+                dir: Direction::None,
+            },
+        ];
+        for command in commands {
+            assert_eq!(
+                command.encode(),
+                Err(CommandError::NoDirection(command.name()))
+            );
         }
     }
 
-    false
+    #[test]
+    fn every_opcode_round_trips() {
+        let opcodes = [
+            OP_SET_LEVEL,
+            OP_SET_LIVES,
+            OP_SET_SEED,
+            OP_STEP_TICKS,
+            OP_REQUEST_STATE,
+            OP_SET_SPEED,
+            OP_QUEUE_MOVE,
+            OP_CLEAR_MOVES,
+            OP_POSE,
+            OP_GRANT_LIFE,
+            OP_REMOVE_LIFE,
+            OP_PLACE_PACMAN,
+            OP_RESTORE_PELLET,
+            OP_END_LEVEL,
+            OP_REWIND,
+            OP_TAKE_GHOST,
+            OP_RELEASE_GHOST,
+            OP_STEER_GHOST,
+        ];
+        let encoded: Vec<u8> = all_commands()
+            .iter()
+            .map(|command| command.encode().unwrap())
+            .filter(|msg| msg[0] == FRAME_START)
+            .map(|msg| msg[2])
+            .collect();
+        for opcode in opcodes {
+            assert!(encoded.contains(&opcode), "opcode {} not covered", opcode);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_lengths() {
+        assert_eq!(Command::parse(&[]), Err(CommandError::Empty));
+        assert!(matches!(
+            Command::parse(b"x\x05"),
+            Err(CommandError::BadLength { command: b'x', .. })
+        ));
+        assert!(matches!(
+            Command::parse(&[FRAME_START, PROTOCOL_VERSION, OP_SET_LEVEL]),
+            Err(CommandError::BadLength { .. })
+        ));
+
+        // Declared length disagreeing with the payload
+        let mut msg = frame(OP_SET_LEVEL, &[3]);
+        msg[3] = 2;
+        assert!(matches!(
+            Command::parse(&msg),
+            Err(CommandError::BadLength { .. })
+        ));
+
+        // Payload of the wrong size for the opcode
+        assert_eq!(
+            Command::parse(&frame(OP_STEP_TICKS, &[1])),
+            Err(CommandError::BadLength {
+                command: OP_STEP_TICKS,
+                expected: 2,
+                actual: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_commands() {
+        assert_eq!(
+            Command::parse(b"z"),
+            Err(CommandError::UnknownCommand(b'z'))
+        );
+        assert_eq!(
+            Command::parse(&frame(0x30, &[])),
+            Err(CommandError::UnknownOpcode(0x30))
+        );
+        let mut msg = frame(OP_REQUEST_STATE, &[]);
+        msg[1] = PROTOCOL_VERSION + 1;
+        assert_eq!(
+            Command::parse(&msg),
+            Err(CommandError::UnsupportedVersion(PROTOCOL_VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        let ghost = NUM_COLORS as u8;
+        for opcode in [OP_TAKE_GHOST, OP_RELEASE_GHOST] {
+            assert_eq!(
+                Command::parse(&frame(opcode, &[ghost])),
+                Err(CommandError::InvalidValue {
+                    command: opcode,
+                    value: ghost as u64,
+                })
+            );
+        }
+        assert!(matches!(
+            Command::parse(&frame(OP_STEER_GHOST, &[ghost, 0])),
+            Err(CommandError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            Command::parse(&frame(OP_STEER_GHOST, &[0, 4])),
+            Err(CommandError::InvalidValue { .. })
+        ));
+        let mut payload = 5u64.to_be_bytes().to_vec();
+        payload.push(4);
+        assert!(matches!(
+            Command::parse(&frame(OP_QUEUE_MOVE, &payload)),
+            Err(CommandError::InvalidValue { .. })
+        ));
+
+        // Values only checked against the game when applied
+        let mut gs = GameState::new();
+        for seconds in [0, REWIND_MAX_SECONDS + 1] {
+            assert_eq!(
+                Command::Rewind(seconds).apply(&mut gs),
+                Err(CommandError::InvalidValue {
+                    command: OP_REWIND,
+                    value: seconds as u64,
+                })
+            );
+        }
+        assert!(Command::SetLevel(0).apply(&mut gs).is_err());
+        assert!(Command::SetSpeed(0).apply(&mut gs).is_err());
+    }
 }
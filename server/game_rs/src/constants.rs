@@ -1,6 +1,7 @@
 // constants.rs - All game constants and maze data, ported from Go variables.go
 
 use crate::direction::Direction;
+use crate::fruit::{Fruit, FruitSpec};
 use crate::location::LocationState;
 
 // Maze dimensions
@@ -12,6 +13,18 @@ pub const INIT_UPDATE_PERIOD: u8 = 12;
 pub const LEVEL_DURATION: u16 = 960; // 8 minutes at 24 fps, update period = 12
 pub const LEVEL_PENALTY_DURATION: u16 = 240; // 2 min (24fps, update period = 12)
 
+// Longest path an absolute (CV) move is walked along, collecting pellets
+pub const MAX_INTERPOLATION_LEN: usize = 11;
+
+// Most Pacman moves that can wait in the move queue
+pub const MAX_QUEUED_MOVES: usize = 32;
+
+// Cells Pacman can move past a buffered turn before it expires
+pub const BUFFERED_TURN_CELLS: u8 = 3;
+
+// How far back a referee can rewind the game, in seconds
+pub const REWIND_MAX_SECONDS: u8 = 10;
+
 // Game modes
 pub const PAUSED: u8 = 0;
 pub const SCATTER: u8 = 1;
@@ -31,6 +44,10 @@ pub const MODE_DURATIONS: [u8; NUM_MODES] = [
 pub const INIT_LEVEL: u8 = 1;
 pub const INIT_LIVES: u8 = 3;
 
+// Bonus lives (when enabled in the rules)
+pub const BONUS_LIFE_SCORE: u32 = 10000;
+pub const MAX_LIVES: u8 = 5;
+
 // Ghost colors
 pub const RED: u8 = 0;
 pub const PINK: u8 = 1;
@@ -88,6 +105,20 @@ pub const GHOST_TRAPPED_STEPS: [u8; NUM_COLORS] = [
     32, // orange
 ];
 
+// Dot counter house release: personal pellet limits by level (level 3+ uses the last row)
+pub const HOUSE_DOT_LIMITS: [[u16; NUM_COLORS]; 3] = [
+    [0, 0, 30, 60], // level 1
+    [0, 0, 0, 50],  // level 2
+    [0, 0, 0, 0],   // level 3+
+];
+
+// Dot counter house release: global pellet limits (used after a death)
+pub const HOUSE_GLOBAL_DOT_LIMITS: [u16; NUM_COLORS] = [0, 7, 17, 32];
+
+// Steps without a pellet before the next ghost is forced out (4s, then 3s from level 5)
+pub const HOUSE_PELLET_TIMER_STEPS: u16 = 8;
+pub const HOUSE_PELLET_TIMER_STEPS_LATE: u16 = 6;
+
 // Ghost fright duration
 pub const GHOST_FRIGHT_STEPS: u8 = 40;
 
@@ -95,6 +126,19 @@ pub const GHOST_FRIGHT_STEPS: u8 = 40;
 pub const FRUIT_DURATION: u8 = 30;
 pub const FRUIT_POINTS: u16 = 100;
 
+// Fruit table, by first level of each band (the alternate spawn, if any, is
+// used for the second fruit of a level)
+pub const FRUIT_TABLE: [FruitSpec; 8] = [
+    FruitSpec::new(1, Fruit::Cherry, FRUIT_POINTS, FRUIT_DURATION, None),
+    FruitSpec::new(2, Fruit::Strawberry, 300, FRUIT_DURATION, None),
+    FruitSpec::new(3, Fruit::Orange, 500, FRUIT_DURATION, None),
+    FruitSpec::new(5, Fruit::Apple, 700, FRUIT_DURATION, Some((5, 13))),
+    FruitSpec::new(7, Fruit::Melon, 1000, FRUIT_DURATION, Some((5, 13))),
+    FruitSpec::new(9, Fruit::Galaxian, 2000, 24, Some((5, 13))),
+    FruitSpec::new(11, Fruit::Bell, 3000, 24, Some((5, 13))),
+    FruitSpec::new(13, Fruit::Key, 5000, 20, Some((5, 13))),
+];
+
 // Pellets
 pub const INIT_PELLET_COUNT: u16 = 244;
 pub const PELLET_POINTS: u16 = 10;
@@ -111,6 +155,38 @@ pub const FRUIT_THRESHOLD_2: u16 = 74;
 pub const ANGER_THRESHOLD_1: u16 = 20;
 pub const ANGER_THRESHOLD_2: u16 = 10;
 
+// Speed model (percent of one cell per update period), by first level of each band
+// [first level, pacman, pacman frightened, ghost, ghost frightened, ghost in tunnel,
+//  elroy 1, elroy 2]
+pub const SPEED_TABLE: [[u8; 8]; 4] = [
+    [1, 80, 90, 75, 50, 40, 80, 85],
+    [2, 90, 95, 85, 55, 45, 90, 95],
+    [5, 100, 100, 95, 60, 50, 100, 105],
+    [21, 90, 90, 95, 95, 50, 100, 105],
+];
+
+// Speed of an eaten ghost returning to the ghost house
+pub const GHOST_EATEN_SPEED: u8 = 200;
+
+// Cells moved per update by eyes, without the speed model
+pub const GHOST_EYES_STEPS: u8 = 2;
+
+// Cruise Elroy (red ghost anger), by first level of each band
+// [first level, pellets left for elroy 1] (elroy 2 starts at half as many)
+pub const ELROY_THRESHOLDS: [[u16; 2]; 8] = [
+    [1, 20],
+    [2, 30],
+    [3, 40],
+    [6, 50],
+    [9, 60],
+    [12, 80],
+    [15, 100],
+    [19, 120],
+];
+
+// Without the speed model, Elroy takes an extra step every N updates (by elroy level)
+pub const ELROY_EXTRA_STEP_PERIODS: [u8; 3] = [0, 8, 4];
+
 // Initial pellet layout as bit arrays (column 0 = bit 0 on the right)
 pub const INIT_PELLETS: [u32; MAZE_ROWS as usize] = [
     0b0000_0000000000000000000000000000, // row 0
@@ -6,62 +6,103 @@ use tokio::sync::mpsc;
 use tokio::time;
 use tracing::{info, warn};
 
-use crate::commands::interpret_command;
+use crate::clients::ClientMessage;
+use crate::commands::{
+    execute_client_command, CommandEffect, CommandError, CommandResponse, CommandResult,
+};
+use crate::events::EventRecord;
 use crate::logging::Logging;
+use crate::referee::StateHistory;
 use crate::state::GameState;
+use crate::stats::MatchStats;
 
 pub struct GameEngine {
     pub state: GameState,
     pub logger: Logging,
+    pub stats: MatchStats,
+    pub history: StateHistory,
+    clock_rate: u32,
     tick_duration: Duration,
 }
 
 impl GameEngine {
     pub fn new(clock_rate: u32) -> Self {
-        let tick_duration = Duration::from_micros(1_000_000 / clock_rate as u64);
+        let tick_duration = Duration::from_micros(1_000_000 / clock_rate.max(1) as u64);
 
         Self {
             state: GameState::new(),
             logger: Logging::new(false),
+            stats: MatchStats::new(clock_rate),
+            history: StateHistory::new(clock_rate),
+            clock_rate,
             tick_duration,
         }
     }
 
+    /// Start a new match, keeping the rules and any events not yet forwarded
+    pub fn reset(&mut self) {
+        info!("STATS: {}", self.stats.report_json());
+        self.stats = MatchStats::new(self.clock_rate);
+        self.history.clear();
+
+        let events = std::mem::take(&mut self.state.events);
+        self.state = GameState::with_rules(self.state.rules.clone());
+        self.state.events = events;
+        self.state.update_all_ghosts();
+        self.state.handle_step_events();
+        self.state.plan_all_ghosts();
+    }
+
+    /// Carry out what an applied command asks of the engine (a rewind past
+    /// the recorded history fails the command)
+    pub fn apply_effect(&mut self, result: CommandResult) -> CommandResult {
+        match result {
+            Ok(CommandEffect::Reset) => self.reset(),
+            Ok(CommandEffect::StepTicks(ticks)) => self.state.step_ticks(ticks),
+            Ok(CommandEffect::Rewind(seconds)) if !self.rewind(seconds) => {
+                return Err(CommandError::NoHistory(seconds));
+            }
+            _ => {}
+        }
+        result
+    }
+
+    /// Go back to the game state from a number of seconds ago, if recorded
+    pub fn rewind(&mut self, seconds: u8) -> bool {
+        let ticks = seconds as u64 * self.clock_rate as u64;
+        match self.history.rewind(self.state.curr_ticks, ticks) {
+            Some(snapshot) => {
+                let action = format!("rewind {} seconds", seconds);
+                self.state.log_referee(action);
+                self.state.restore_snapshot(snapshot);
+                true
+            }
+            None => {
+                warn!("ERR: No state recorded {} seconds ago", seconds);
+                false
+            }
+        }
+    }
+
     /// Run the main game loop (async, matching Go's runLoop)
     pub async fn run_loop(
         &mut self,
         output_tx: mpsc::Sender<String>,
-        mut input_rx: mpsc::Receiver<Vec<u8>>,
+        event_tx: mpsc::Sender<EventRecord>,
+        mut input_rx: mpsc::Receiver<ClientMessage>,
         mut quit_rx: mpsc::Receiver<()>,
     ) {
         info!("LOG: Game engine started");
 
         let mut interval = time::interval(self.tick_duration);
         let mut just_ticked = true;
+        let mut events_open = true;
 
         loop {
-            // Step 1: Update if ready
-            if just_ticked && self.state.update_ready() {
-                // Update all ghosts
-                self.state.update_all_ghosts();
-
-                // Try to respawn pacman
-                self.state.try_respawn_pacman();
-
-                // Pause on update if flagged
-                if self.state.get_pause_on_update() {
-                    self.state.pause();
-                    self.state.set_pause_on_update(false);
-                }
-
-                // Check collisions
-                self.state.check_collisions();
-
-                // Handle step events (mode changes, penalties, fruit)
-                self.state.handle_step_events();
-
-                // Plan next ghost moves
-                self.state.plan_all_ghosts();
+            // Step 1: Update if ready (ghosts, collisions, step events, plans)
+            if just_ticked {
+                self.state.update();
+                self.history.record(&self.state);
             }
 
             // Step 2: Serialize and send state
@@ -71,20 +112,45 @@ impl GameEngine {
                 break;
             }
 
-            // Step 3: Read commands
-            loop {
-                match input_rx.try_recv() {
-                    Ok(msg) => {
-                        let rst = interpret_command(&msg, &mut self.state, &self.logger);
-                        if rst {
-                            self.state = GameState::new();
-                            self.state.update_all_ghosts();
-                            self.state.handle_step_events();
-                            self.state.plan_all_ghosts();
-                            just_ticked = true;
+            // Step 2b: Record and forward any events (the game keeps running without a listener)
+            self.stats
+                .record_frame(self.state.get_curr_ticks(), self.state.is_paused());
+            for record in self.state.drain_events() {
+                if self.stats.record(&record) {
+                    info!("STATS: {}", self.stats.report_json());
+                }
+                if events_open && event_tx.send(record).await.is_err() {
+                    warn!("WARN: Event channel closed");
+                    events_open = false;
+                }
+            }
+
+            // Step 3: Read commands (only those the sender's role allows), and respond
+            while let Ok(input) = input_rx.try_recv() {
+                let client = &input.client;
+                let result =
+                    execute_client_command(&input.msg, client, &mut self.state, &self.logger);
+                let result = self.apply_effect(result);
+                input.respond(CommandResponse::new(
+                    client.id,
+                    self.state.curr_ticks,
+                    &input.msg,
+                    &result,
+                ));
+
+                match result {
+                    Ok(CommandEffect::SendState) => {
+                        // A closed channel is caught when the next state is sent
+                        if output_tx.send(self.state.serialize()).await.is_err() {
+                            break;
                         }
                     }
-                    Err(_) => break,
+                    // (a failed rewind was already logged)
+                    Ok(_)
+                    | Err(CommandError::Empty)
+                    | Err(CommandError::Forbidden { .. })
+                    | Err(CommandError::NoHistory(_)) => {}
+                    Err(err) => warn!("ERR: Invalid command ({}). Ignoring...", err),
                 }
             }
 
@@ -100,6 +166,7 @@ impl GameEngine {
             tokio::select! {
                 _ = interval.tick() => {},
                 _ = quit_rx.recv() => {
+                    info!("STATS: {}", self.stats.report_json());
                     info!("LOG: Game engine quit");
                     return;
                 }
@@ -107,3 +174,62 @@ impl GameEngine {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::{ClientInfo, ClientRole};
+    use crate::commands::{Command, ResponseStatus};
+
+    #[test]
+    fn rewinding_past_the_history_fails() {
+        let mut engine = GameEngine::new(24);
+        engine.state.step_ticks(10);
+        assert_eq!(
+            engine.apply_effect(Ok(CommandEffect::Rewind(5))),
+            Err(CommandError::NoHistory(5))
+        );
+        assert_eq!(engine.state.curr_ticks, 10);
+    }
+
+    #[tokio::test]
+    async fn responses_follow_the_effects() {
+        // Start with no history before tick 100
+        let mut engine = GameEngine::new(24);
+        engine.state.step_ticks(100);
+        let (output_tx, _output_rx) = mpsc::channel(64);
+        let (event_tx, _event_rx) = mpsc::channel(64);
+        let (input_tx, input_rx) = mpsc::channel(8);
+        let (quit_tx, quit_rx) = mpsc::channel(1);
+        let (reply_tx, mut reply_rx) = mpsc::channel(8);
+
+        let referee = ClientInfo::new(1, ClientRole::Referee);
+        for cmd in [Command::StepTicks(5), Command::Rewind(3)] {
+            let msg = ClientMessage::new(referee.clone(), cmd.encode().unwrap())
+                .with_reply(reply_tx.clone());
+            input_tx.send(msg).await.unwrap();
+        }
+
+        let client = async {
+            let step = reply_rx.recv().await.unwrap();
+            let rewind = reply_rx.recv().await.unwrap();
+            quit_tx.send(()).await.unwrap();
+            (step, rewind)
+        };
+        let (_, (step, rewind)) = tokio::join!(
+            engine.run_loop(output_tx, event_tx, input_rx, quit_rx),
+            client
+        );
+
+        // The step is reported at the tick it ended on
+        assert_eq!(step.status, ResponseStatus::Applied);
+        assert_eq!(step.tick, 105);
+
+        // Nothing was recorded 3 seconds (72 ticks) ago
+        assert_eq!(rewind.status, ResponseStatus::Invalid);
+        assert_eq!(
+            rewind.error.as_deref(),
+            Some("no state recorded 3 seconds ago")
+        );
+    }
+}
@@ -0,0 +1,137 @@
+// events.rs - Structured game events, queued on the game state
+
+use serde::{Deserialize, Serialize};
+
+use crate::clients::ClientRole;
+use crate::fruit::Fruit;
+use crate::state::GameState;
+
+// Oldest events are dropped once this many are waiting to be drained
+pub const MAX_QUEUED_EVENTS: usize = 1024;
+
+/// A notable game transition
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "type")]
+pub enum GameEvent {
+    ModeChanged { from: u8, to: u8 },
+    Paused,
+    Resumed,
+    PacmanCaught { ghost: u8, row: i8, col: i8 },
+    LifeLost { lives: u8 },
+    GameOver,
+    LivesSet { lives: u8 },
+    BonusLife { lives: u8, score: u32 },
+    LevelChanged { level: u8 },
+    GhostEaten { ghost: u8, points: u16, combo: u8 },
+    PelletEaten { row: i8, col: i8 },
+    SuperPelletEaten { row: i8, col: i8 },
+    FruitSpawned { fruit: Fruit, row: i8, col: i8 },
+    FruitEaten { fruit: Fruit, points: u16 },
+    LongGamePenalty { update_period: u8 },
+    ElroyChanged { elroy: u8 },
+    GhostReleased { ghost: u8 },
+    PacmanJumped { from_row: i8, from_col: i8, row: i8, col: i8, path_len: u16 },
+    CommandRejected { client: u32, role: ClientRole, command: String },
+}
+
+fn is_first_pacman(pacman: &u8) -> bool {
+    *pacman == 0
+}
+
+/// An event, stamped with the tick it happened on (and the Pacman acting, in
+/// multi-agent games)
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct EventRecord {
+    pub tick: u64,
+    #[serde(default, skip_serializing_if = "is_first_pacman")]
+    pub pacman: u8,
+    #[serde(flatten)]
+    pub event: GameEvent,
+}
+
+impl GameState {
+    /// Queue an event at the current tick
+    pub fn emit(&mut self, event: GameEvent) {
+        if self.events.len() >= MAX_QUEUED_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(EventRecord {
+            tick: self.curr_ticks,
+            pacman: self.curr_pacman,
+            event,
+        });
+    }
+
+    /// Take all queued events, oldest first
+    pub fn drain_events(&mut self) -> Vec<EventRecord> {
+        self.events.drain(..).collect()
+    }
+
+    /// Take all queued events as a JSON array
+    pub fn drain_events_json(&mut self) -> String {
+        serde_json::to_string(&self.drain_events()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::direction::Direction;
+    use crate::rules::GameRules;
+
+    fn events(gs: &mut GameState) -> Vec<GameEvent> {
+        gs.drain_events()
+            .into_iter()
+            .map(|record| record.event)
+            .collect()
+    }
+
+    #[test]
+    fn transitions_are_queued_in_order() {
+        let mut gs = GameState::new();
+        gs.play();
+        gs.move_pacman_dir(Direction::Left);
+        gs.pause();
+        assert_eq!(
+            events(&mut gs),
+            [
+                GameEvent::Resumed,
+                GameEvent::PelletEaten { row: 23, col: 12 },
+                GameEvent::Paused,
+            ]
+        );
+        assert!(gs.drain_events().is_empty());
+    }
+
+    #[test]
+    fn events_are_stamped_and_serialized_flat() {
+        let mut gs = GameState::with_rules(GameRules {
+            pacmen: 2,
+            ..Default::default()
+        });
+        gs.curr_ticks = 42;
+        gs.emit(GameEvent::LifeLost { lives: 2 });
+        gs.with_pacman(1, |gs| gs.emit(GameEvent::GameOver));
+
+        let json: serde_json::Value = serde_json::from_str(&gs.drain_events_json()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {"tick": 42, "type": "LifeLost", "lives": 2},
+                {"tick": 42, "pacman": 1, "type": "GameOver"},
+            ])
+        );
+    }
+
+    #[test]
+    fn oldest_events_are_dropped_past_the_cap() {
+        let mut gs = GameState::new();
+        for tick in 0..MAX_QUEUED_EVENTS as u64 + 5 {
+            gs.curr_ticks = tick;
+            gs.emit(GameEvent::Paused);
+        }
+        let records = gs.drain_events();
+        assert_eq!(records.len(), MAX_QUEUED_EVENTS);
+        assert_eq!(records[0].tick, 5);
+    }
+}
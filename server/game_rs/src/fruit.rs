@@ -0,0 +1,176 @@
+// fruit.rs - Fruit types, with level-dependent points and spawn locations
+
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::constants::*;
+use crate::events::GameEvent;
+use crate::state::GameState;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum Fruit {
+    Cherry,
+    Strawberry,
+    Orange,
+    Apple,
+    Melon,
+    Galaxian,
+    Bell,
+    Key,
+}
+
+/// An entry of the fruit table
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FruitSpec {
+    pub first_level: u8,
+    pub fruit: Fruit,
+    pub points: u16,
+    pub duration: u8,
+    pub alt_spawn: Option<(i8, i8)>,
+}
+
+impl FruitSpec {
+    pub const fn new(
+        first_level: u8,
+        fruit: Fruit,
+        points: u16,
+        duration: u8,
+        alt_spawn: Option<(i8, i8)>,
+    ) -> Self {
+        Self {
+            first_level,
+            fruit,
+            points,
+            duration,
+            alt_spawn,
+        }
+    }
+}
+
+impl Fruit {
+    /// Get the fruit table entry for the fruit spawned at a level
+    pub fn spec_for_level(level: u8) -> &'static FruitSpec {
+        FRUIT_TABLE
+            .iter()
+            .take_while(|spec| spec.first_level <= level)
+            .last()
+            .unwrap_or(&FRUIT_TABLE[0])
+    }
+
+    /// Get the fruit table entry for this fruit
+    pub fn spec(&self) -> &'static FruitSpec {
+        FRUIT_TABLE
+            .iter()
+            .find(|spec| spec.fruit == *self)
+            .unwrap_or(&FRUIT_TABLE[0])
+    }
+
+    /// Points awarded for eating this fruit
+    pub fn points(&self) -> u16 {
+        self.spec().points
+    }
+}
+
+impl fmt::Display for Fruit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fruit::Cherry => write!(f, "cherry"),
+            Fruit::Strawberry => write!(f, "strawberry"),
+            Fruit::Orange => write!(f, "orange"),
+            Fruit::Apple => write!(f, "apple"),
+            Fruit::Melon => write!(f, "melon"),
+            Fruit::Galaxian => write!(f, "galaxian"),
+            Fruit::Bell => write!(f, "bell"),
+            Fruit::Key => write!(f, "key"),
+        }
+    }
+}
+
+impl GameState {
+    /// Spawn the fruit for the current level (the second fruit of a level
+    /// uses the alternate spawn location, if the fruit has one)
+    pub fn spawn_fruit(&mut self, second: bool) {
+        let spec = Fruit::spec_for_level(self.get_level());
+        self.fruit_type = spec.fruit;
+
+        let (row, col) = match spec.alt_spawn {
+            Some(alt) if second => alt,
+            _ => fruit_spawn_loc().get_coords(),
+        };
+        self.fruit_loc.update_coords(row, col);
+        self.set_fruit_steps(spec.duration);
+
+        info!(
+            "GAME: Fruit spawned ({} at ({}, {})) (t = {})",
+            spec.fruit,
+            row,
+            col,
+            self.curr_ticks
+        );
+        self.emit(GameEvent::FruitSpawned {
+            fruit: spec.fruit,
+            row,
+            col,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fruit_follows_the_level() {
+        let fruits = [
+            (1, Fruit::Cherry),
+            (2, Fruit::Strawberry),
+            (4, Fruit::Orange),
+            (6, Fruit::Apple),
+            (8, Fruit::Melon),
+            (10, Fruit::Galaxian),
+            (12, Fruit::Bell),
+            (13, Fruit::Key),
+            (255, Fruit::Key),
+        ];
+        for (level, fruit) in fruits {
+            assert_eq!(Fruit::spec_for_level(level).fruit, fruit);
+        }
+        assert_eq!(Fruit::Cherry.points(), FRUIT_POINTS);
+        assert_eq!(Fruit::Key.points(), 5000);
+    }
+
+    #[test]
+    fn second_fruit_uses_the_alternate_spawn() {
+        let mut gs = GameState::new();
+        gs.spawn_fruit(true);
+        assert_eq!(gs.fruit_loc.get_coords(), fruit_spawn_loc().get_coords());
+
+        gs.set_level(5);
+        gs.spawn_fruit(false);
+        assert_eq!(gs.get_fruit_type(), Fruit::Apple);
+        assert_eq!(gs.fruit_loc.get_coords(), fruit_spawn_loc().get_coords());
+        gs.spawn_fruit(true);
+        assert_eq!(gs.fruit_loc.get_coords(), (5, 13));
+    }
+
+    #[test]
+    fn eating_fruit_scores_its_points() {
+        let mut gs = GameState::new();
+        gs.set_level(13);
+        gs.spawn_fruit(false);
+        assert!(gs.fruit_exists());
+        assert_eq!(gs.get_fruit_steps(), 20);
+
+        let (row, col) = gs.fruit_loc.get_coords();
+        gs.pacman_loc.update_coords(row, col);
+        gs.collect_pellet(row, col);
+        assert!(!gs.fruit_exists());
+        assert_eq!(gs.curr_score, 5000);
+        let eaten = GameEvent::FruitEaten {
+            fruit: Fruit::Key,
+            points: 5000,
+        };
+        assert!(gs.events.iter().any(|record| record.event == eaten));
+    }
+}
@@ -1,53 +1,38 @@
-use crate::coords::Pos;
-use crate::ghost_state::GameState;
-use std::thread;
-
-const MAZE_ROWS: i8 = 31;
-const MAZE_COLS: i8 = 28;
-const FRUIT_POINTS: i32 = 100;
-const FRUIT_THRESHOLD_1: i32 = 70; 
-const FRUIT_THRESHOLD_2: i32 = 170; 
-const FRUIT_DURATION: u16 = 500;
-const ANGER_THRESHOLD_1: i32 = 190; 
-const ANGER_THRESHOLD_2: i32 = 210;
-const MODE_DURATIONS: [u32; 3] = [200, 800, 1000];
-const UP: u8 = 0;
-const DOWN: u8 = 0;
-const LEFT: u8 = 0;
-const RIGHT: u8 = 0;
-const NONE: u8 = 0;
-const GHOST_FRIGHT_STEPS= 0; 
-const LEVEL_DURATION =0;
-const RED: u8= 0; 
-const PINK: u8 =1; 
-const CYAN: u8 =2; 
-const ORANGE: u8 =3;
-const COMBO_MULTIPLIER: i32=0;
+// game_helpers.rs - Game logic helpers, ported from Go game_helpers.go
+
+use tracing::{error, info, warn};
+
+use crate::constants::*;
+use crate::direction::{Direction, NUM_DIRS};
+use crate::events::GameEvent;
+use crate::rules::AngerModel;
+use crate::state::GameState;
+
 /*
 Get a bit within an unsigned integer (treating the integers
 in pellets and walls as bit arrays)
 */
-pub fn get_bit<N, I>(num: N, bit_idx: I) -> bool 
+pub fn get_bit<N, I>(num: N, bit_idx: I) -> bool
 where
     N: Into<u32> + Copy,
     I: Into<u32> + Copy,
 {
     let num_val: u32 = num.into();
     let bit_idx_val: u32 = bit_idx.into();
-	/*
-		Uses bitwise operation magic (not really, look up how the >> and &
-		operators work if you're interested)
-	*/
-	(num_val >> bit_idx_val) & 1 == 1
+    /*
+        Uses bitwise operation magic (not really, look up how the >> and &
+        operators work if you're interested)
+    */
+    (num_val >> bit_idx_val) & 1 == 1
 }
 
 /*
 Get a bit within an unsigned integer (treating the integers in pellets
 and walls as bit arrays)
 */
-fn modify_bit<N, I>(num: &mut N, bit_idx: I, bit_val: bool)
+pub fn modify_bit<N, I>(num: &mut N, bit_idx: I, bit_val: bool)
 where
-    N: From<u32> + Into<u32> + Copy,
+    N: TryFrom<u32> + Into<u32> + Copy,
     I: Into<u32> + Copy,
 {
     let bit_idx_val: u32 = bit_idx.into();
@@ -59,7 +44,26 @@ where
     } else {
         num_val & (!mask)
     };
-    *num = N::from(new_num_val);
+    if let Ok(new_num) = N::try_from(new_num_val) {
+        *num = new_num;
+    }
+}
+
+// What happened to a requested Pacman move
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MoveOutcome {
+    Moved,
+    Paused,
+    Blocked,
+    Buffered,
+    Unconfirmed,
+}
+
+// A grid cell, used by the path-finding helpers
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Pos {
+    pub r: i8,
+    pub c: i8,
 }
 
 impl Pos {
@@ -85,27 +89,26 @@ impl Pos {
     }
 }
 
-impl GameState{
-/****************************** Timing Functions ******************************/
+impl GameState {
+    /****************************** Timing Functions ******************************/
 
     // Determines if the game state is ready to update
     pub fn update_ready(&self) -> bool {
-
         // Get the current ticks value
-        let curr_ticks = self.get_curr_tickets();
+        let curr_ticks = self.get_curr_ticks();
 
-        // Get the update period (uint16 to match the type of current ticks)
-        let update_period = self.get_update_period() as u16;
+        // Get the update period (uint64 to match the type of current ticks)
+        let update_period = self.get_update_period() as u64;
 
         // Update if the update period divides the current ticks
-        curr_ticks%update_period == 0
+        curr_ticks.is_multiple_of(update_period)
     }
 
-    /**************************** Positional fnctions ****************************/
+    /**************************** Positional functions ****************************/
 
     // Determines if a position is within the bounds of the maze
     pub fn in_bounds(&self, row: i8, col: i8) -> bool {
-         (row >= 0 && row < MAZE_ROWS) && (col >= 0 && col < MAZE_COLS)
+        (0..MAZE_ROWS).contains(&row) && (0..MAZE_COLS).contains(&col)
     }
 
     // Determines if a pellet is at a given location
@@ -114,10 +117,6 @@ impl GameState{
             return false;
         }
 
-        // (Read) lock the pellets array
-        // gs.muPellets.RLock()
-        // defer gs.muPellets.RUnlock()
-
         // Returns the bit of the pellet row corresponding to the column
         get_bit(self.pellets[row as usize], col as u32)
     }
@@ -127,11 +126,14 @@ impl GameState{
     Returns the number of pellets that are left
     */
     pub fn collect_pellet(&mut self, row: i8, col: i8) {
-
         // Collect fruit, if applicable
-        if self.fruit_exists() && self.pacman_loc.collides_with(self.fruit_loc) {
+        if self.fruit_exists() && self.pacman_loc.collides_with(&self.fruit_loc) {
             self.set_fruit_steps(0);
-            self.increment_score(FRUIT_POINTS);
+            self.increment_score(self.fruit_type.points());
+            self.emit(GameEvent::FruitEaten {
+                fruit: self.fruit_type,
+                points: self.fruit_type.points(),
+            });
         }
 
         // If there's no pellet, return
@@ -142,43 +144,46 @@ impl GameState{
         // If we can clear the pellet's bit, decrease the number of pellets
         modify_bit(&mut self.pellets[row as usize], col as u32, false);
         self.decrement_num_pellets();
+        self.count_house_pellet();
 
         // If the we are in particular rows and columns, it is a super pellet
         let super_pellet = ((row == 3) || (row == 23)) && ((col == 1) || (col == 26));
 
         // Make all the ghosts frightened if a super pellet is collected
         if super_pellet {
+            self.emit(GameEvent::SuperPelletEaten { row, col });
             self.frighten_all_ghosts();
+        } else {
+            self.emit(GameEvent::PelletEaten { row, col });
         }
 
         // Update the score, depending on the pellet type
         if super_pellet {
-            self.increment_score(super_pellet_points);
+            self.increment_score(SUPER_PELLET_POINTS);
         } else {
-            self.increment_score(pellet_points);
+            self.increment_score(PELLET_POINTS);
         }
 
         // Act depending on the number of pellets left over
         let num_pellets = self.get_num_pellets();
 
         // Spawn fruit, if applicable
-        if (num_pellets == FRUIT_THRESHOLD_1) && !self.fruit_exists() {
-            self.set_fruit_steps(FRUIT_DURATION);
-        } else if num_pellets == FRUIT_THRESHOLD_2 && !self.fruit_exists() {
-            self.set_fruit_steps(FRUIT_DURATION);
+        if (num_pellets == FRUIT_THRESHOLD_1 || num_pellets == FRUIT_THRESHOLD_2)
+            && !self.fruit_exists()
+        {
+            self.spawn_fruit(num_pellets == FRUIT_THRESHOLD_2);
         }
 
         // Other pellet-related events
-        if num_pellets == ANGER_THRESHOLD_1 { // Ghosts get angry (speeding up)
-            let new_period = std::cmp::max(1, self.get_update_period() as i32 - 2) as u8;
-            self.set_update_period(new_period);
-            self.set_mode(GameMode::Chase);
-            self.set_mode_steps(MODE_DURATIONS[GameMode::Chase as usize])
-        } else if num_pellets == ANGER_THRESHOLD_2 { // Ghosts get angrier
+        if self.rules.anger_model == AngerModel::Elroy && num_pellets > 0 {
+            // Only red gets angry, depending on the level
+            self.update_elroy();
+        } else if num_pellets == ANGER_THRESHOLD_1 || num_pellets == ANGER_THRESHOLD_2 {
+            // Ghosts get angry (speeding up)
             let new_period = std::cmp::max(1, self.get_update_period() as i32 - 2) as u8;
             self.set_update_period(new_period);
-            self.set_mode(GameMode::Chase);
-            self.set_mode_steps(MODE_DURATIONS[GameMode::Chase as usize]);
+            self.set_mode(CHASE);
+            self.set_mode_steps(MODE_DURATIONS[CHASE as usize]);
         } else if num_pellets == 0 {
             self.level_reset();
             self.increment_level();
@@ -192,53 +197,71 @@ impl GameState{
         }
 
         // Returns the bit of the wall row corresponding to the column
-        get_bit(self.walls[row as usize], col)
+        get_bit(self.walls[row as usize], col as u32)
     }
 
     // Determines if the ghost house is at a given location
     pub fn ghost_spawn_at(&self, row: i8, col: i8) -> bool {
         if !self.in_bounds(row, col) {
-            return false
+            return false;
         }
 
         // Returns the bit of the wall row corresponding to the column
-        ((row >= 13) && (row <= 14)) && ((col >= 11) && (col <= 15))
+        (13..=14).contains(&row) && (11..=15).contains(&col)
     }
 
     // Calculates the squared Euclidean distance between two points
     pub fn dist_sq(&self, row1: i8, col1: i8, row2: i8, col2: i8) -> i32 {
         let dx = row2 as i32 - row1 as i32;
         let dy = col2 as i32 - col1 as i32;
-        dx*dx + dy*dy
+        dx * dx + dy * dy
     }
 
     /***************************** Collision Handling *****************************/
 
-    // Check collisions between Pacman and all the ghosts
-    pub fn check_collisions(& mut self) {
+    // Check collisions between every Pacman and all the ghosts
+    pub fn check_collisions(&mut self) {
+        self.for_each_pacman(|gs| gs.check_pacman_collisions());
+    }
 
+    // Check collisions between the acting Pacman and all the ghosts
+    pub fn check_pacman_collisions(&mut self) {
         // Flag to decide which ghosts should respawn
-        let mut  ghost_respawn_flag: u8 = 0;
+        let mut ghost_respawn_flag: u8 = 0;
 
         // Keep track of how many ghosts need to respawn
         let mut num_ghost_respawns = 0;
 
         // Loop over all the ghosts
-        for i in 0..self.ghosts.len() {
-            let ghost = &mut self.ghosts[i];
-            // Check each collision individually
-            if self.pacman_loc.collides_with(&ghost.loc) {
-
-                // If the ghost was already eaten, skip it
-                if ghost.is_eaten() {
+        for ghost in self.ghosts.iter() {
+            // Check each collision individually (including Pacman and the ghost
+            // swapping cells, which would otherwise let them pass through, and
+            // overlapping footprints if enabled)
+            let swapped = self.pacman_loc.swapped_with(
+                &self.pacman_prev_loc,
+                &ghost.loc,
+                &ghost.prev_loc,
+            );
+            if self.pacman_loc.collides_with(&ghost.loc)
+                || swapped
+                || self.footprints_overlap(ghost)
+            {
+                // If the ghost was already eaten (or is returning as eyes), skip it
+                if ghost.is_eaten() || ghost.is_eyes() {
                     continue;
                 }
 
                 // If the ghost is frightened, Pacman eats it, otherwise Pacman dies
                 if ghost.is_frightened() {
                     modify_bit(&mut ghost_respawn_flag, ghost.color, true);
-                    num_ghost_respawns+=1;
+                    num_ghost_respawns += 1;
                 } else {
+                    let (row, col) = self.pacman_loc.get_coords();
+                    self.emit(GameEvent::PacmanCaught {
+                        ghost: ghost.color,
+                        row,
+                        col,
+                    });
                     self.death_reset();
                     return;
                 }
@@ -250,23 +273,23 @@ impl GameState{
             return;
         }
 
-        // Lock the motion mutex to synchronize with other ghost update routines
-        self.respawn_ghosts(num_ghost_respawns, ghost_respawn_Flag);
+        // Respawn the ghosts that were eaten
+        self.respawn_ghosts(num_ghost_respawns, ghost_respawn_flag);
     }
 
     /***************************** Event-Based Resets *****************************/
 
     // Reset the board (while leaving pellets alone) after Pacman dies
     pub fn death_reset(&mut self) {
-
-        // Acquire the Pacman control lock, to prevent other Pacman movement
-        let _pacman_lock = self.mu_pacman.lock().unwrap();
-
         // Set the game to be paused at the next update
         self.set_pause_on_update(true);
 
+        // Pending moves were meant for the old position
+        self.clear_moves();
+
         // Set Pacman to be in an empty state
-        self.pacman_loc.copy_from(Pos::EMPTY_LOC);
+        self.pacman_loc.copy_from(&empty_loc());
+        self.pacman_prev_loc.copy_from(&empty_loc());
 
         // Decrease the number of lives Pacman has left
         self.decrement_lives();
@@ -275,9 +298,11 @@ impl GameState{
             If the mode is not the initial mode and the ghosts aren't angry,
             change the mode back to the initial mode
         */
-        if self.get_num_pellets() > ANGER_THRESHOLD_1 {
-            self.set_mode(GameMode::Init);
-            self.set_mode_steps(MODE_DURATIONS[GameMode::Init as usize]);
+        if self.rules.anger_model == AngerModel::Elroy
+            || self.get_num_pellets() > ANGER_THRESHOLD_1
+        {
+            self.set_mode(INIT_MODE);
+            self.set_mode_steps(MODE_DURATIONS[INIT_MODE as usize]);
         }
 
         // Set the fruit steps back to 0
@@ -285,20 +310,25 @@ impl GameState{
 
         // Reset all the ghosts to their original locations
         self.reset_all_ghosts();
+        self.reset_house_counters(true);
     }
 
     // Reset the board (including pellets) after Pacman clears a level
-    pub fn level_reset(& mut self) {
-
+    pub fn level_reset(&mut self) {
         // Set the game to be paused at the next update
         self.set_pause_on_update(true);
 
-        // Set Pacman to be in an empty state
-        self.pacman_loc.copy_from(Pos::EMPTY_LOC);
+        // Pending moves were meant for the old position
+        self.clear_moves();
+
+        // Set Pacman (and any other Pacmen) to be in an empty state
+        self.pacman_loc.copy_from(&empty_loc());
+        self.pacman_prev_loc.copy_from(&empty_loc());
+        self.clear_other_pacmen();
 
         // If the mode is not the initial mode, change it
-        self.set_mode(GameMode::Init);
-        self.set_mode_steps(MODE_DURATIONS[GameMode::Init as usize]);
+        self.set_mode(INIT_MODE);
+        self.set_mode_steps(MODE_DURATIONS[INIT_MODE as usize]);
 
         // Reset the level penalty
         self.set_level_steps(LEVEL_DURATION);
@@ -308,61 +338,89 @@ impl GameState{
 
         // Reset all the ghosts to their original locations
         self.reset_all_ghosts();
+        self.reset_house_counters(false);
 
         // Reset the pellet bit array and count
         self.reset_pellets();
+
+        // Red calms down again with a full board
+        self.ghosts[RED as usize].set_elroy(0);
     }
 
     /************************** Motion (Pacman Location) **************************/
 
     // Move Pacman one space in a given direction
-    pub fn move_pacman_dir(&mut self, dir: u8) {
+    pub fn move_pacman_dir(&mut self, dir: Direction) -> MoveOutcome {
+        // Ignore the command if the game is paused
+        if self.is_paused() || self.get_pause_on_update() {
+            return MoveOutcome::Paused;
+        }
 
-        // Acquire the Pacman control lock, to prevent other Pacman movement
-        let _pacman_lock = self.mu_pacman.lock().unwrap();
+        // With continuous motion, moves only turn Pacman
+        if self.rules.continuous_motion {
+            return self.turn_pacman(dir);
+        }
+
+        // A buffered turn takes over once it is legal
+        let heading = self.pacman_loc.dir;
+        let dir = self.resolve_buffered_turn(dir);
+        match self.step_pacman(dir) {
+            MoveOutcome::Blocked => {
+                let outcome = self.buffer_turn(dir, heading);
+
+                // Pacman keeps heading the same way while the turn waits
+                if outcome == MoveOutcome::Buffered {
+                    self.pacman_loc.update_dir(heading);
+                }
+                outcome
+            }
+            outcome => outcome,
+        }
+    }
 
+    // Move Pacman one cell in a direction, unless a wall is in the way
+    pub(crate) fn step_pacman(&mut self, dir: Direction) -> MoveOutcome {
         // Ignore the command if the game is paused
         if self.is_paused() || self.get_pause_on_update() {
-            return;
+            return MoveOutcome::Paused;
         }
 
-        // Shorthand to make computation simpler
-        let p_loc = &mut self.pacman_loc;
-
         // Calculate the next row and column
-        let (next_row, next_col) = p_loc.get_neighbor_coords(dir);
+        let (next_row, next_col) = self.pacman_loc.get_neighbor_coords(dir);
 
         // Update Pacman's direction
-        p_loc.update_dir(dir);
+        self.pacman_loc.update_dir(dir);
 
         // Check if there is a wall at the anticipated location, and return if so
         if self.wall_at(next_row, next_col) {
-            return;
+            return MoveOutcome::Blocked;
         }
 
         // Move Pacman the anticipated spot
-        p_loc.update_coords(next_row, next_col);
+        self.pacman_prev_loc.copy_from(&self.pacman_loc);
+        self.pacman_loc.update_coords(next_row, next_col);
+        self.age_buffered_turn();
         self.collect_pellet(next_row, next_col);
-        self.check_collisions();
+        self.check_pacman_collisions();
+        MoveOutcome::Moved
     }
 
     // Move pacman to destination along shortest path (CV update)
-    pub fn move_pacman_absolute(&mut self, new_row: i8, new_col: i8) {
+    pub fn move_pacman_absolute(&mut self, new_row: i8, new_col: i8) -> MoveOutcome {
         // Don't update position if we're paused
         if self.is_paused() || self.get_pause_on_update() {
-            return;
+            return MoveOutcome::Paused;
         }
 
         // Reject invalid coords
         if self.wall_at(new_row, new_col) {
-            return;
+            self.reject_tracking_update();
+            return MoveOutcome::Blocked;
         }
 
-        let p_loc = &mut self.pacman_loc;
-
         // Reject same coords
-        if p_loc.row == new_row && p_loc.col == new_col {
-            return;
+        if self.pacman_loc.row == new_row && self.pacman_loc.col == new_col {
+            return MoveOutcome::Moved;
         }
 
         // Find likely path
@@ -372,29 +430,46 @@ impl GameState{
         let path = match path {
             Some(p) => p,
             None => {
-                eprintln!("\x1b[31mERR: Failed to find correct path\x1b[0m");
-                return;
+                error!("ERR: Failed to find correct path");
+                self.reject_tracking_update();
+                return MoveOutcome::Blocked;
             }
         };
 
-        // The new position is far from the old one, let's not traverse the path
-        if path.len() > 11 {
-            eprintln!(
-                "\x1b[35mWARN: Interpolated path too long! \
-                 Tracking performance is likely degraded\x1b[0m"
-            );
+        // With the CV filter, far-away positions need confirming first
+        if self.rules.cv_filter.is_some() {
+            return self.track_pacman(new_row, new_col, path);
+        }
 
-            // Acquire the Pacman control lock, to prevent other Pacman movement
-            let _pacman_lock = self.mu_pacman.lock().unwrap();
+        // The new position is far from the old one, let's not traverse the path
+        if path.len() > self.max_interpolation_len() {
+            warn!("WARN: Interpolated path too long! Tracking performance is likely degraded");
+            let (from_row, from_col) = self.pacman_loc.get_coords();
+            self.emit(GameEvent::PacmanJumped {
+                from_row,
+                from_col,
+                row: new_row,
+                col: new_col,
+                path_len: path.len() as u16,
+            });
 
-            // Move Pacman directly to the given position
-            self._pacman_lock.update_coords(new_row, new_col);
-            self.collect_pellet(new_row, new_col);
-            // gs.collect_pellet(newRow, newCol);
-            self.check_collisions();
-            return;
+            return self.teleport_pacman(new_row, new_col);
         }
 
+        self.walk_pacman_path(path)
+    }
+
+    // Move Pacman directly to a position (it didn't pass any cells)
+    pub(crate) fn teleport_pacman(&mut self, row: i8, col: i8) -> MoveOutcome {
+        self.pacman_prev_loc.copy_from(&empty_loc());
+        self.pacman_loc.update_coords(row, col);
+        self.collect_pellet(row, col);
+        self.check_pacman_collisions();
+        MoveOutcome::Moved
+    }
+
+    // Move Pacman along a path, one cell at a time
+    pub(crate) fn walk_pacman_path(&mut self, path: Vec<Pos>) -> MoveOutcome {
         let mut prev_pos = Pos {
             r: self.pacman_loc.row,
             c: self.pacman_loc.col,
@@ -402,38 +477,104 @@ impl GameState{
         // Move Pacman along the detected route
         for next_pos in path.into_iter() {
             let dir = if next_pos.r < prev_pos.r {
-                UP
+                Direction::Up
             } else if next_pos.c < prev_pos.c {
-                LEFT
+                Direction::Left
             } else if next_pos.r > prev_pos.r {
-                DOWN
+                Direction::Down
             } else {
-                RIGHT
+                Direction::Right
             };
-            self.move_pacman_dir(dir);
+            self.step_pacman(dir);
             prev_pos = next_pos;
         }
+        MoveOutcome::Moved
     }
 
     // Find likely/shortest path to new coords
-    // precondition: lock pacman pos
     pub fn find_likely_path(&self, new_row: i8, new_col: i8) -> Option<Vec<Pos>> {
-        // Begin breadth-first search
         let start = Pos {
             r: self.pacman_loc.row,
             c: self.pacman_loc.col,
         };
-        let mut queue = std::collections::VecDeque::from(vec![start]); // Rust's BFS queue
+        let target = Pos { r: new_row, c: new_col };
+
+        // With interpolation, prefer the shortest path that keeps Pacman's heading
+        if self.rules.interpolation.is_some() {
+            return self.find_heading_path(start, target, self.pacman_loc.dir);
+        }
+        self.find_path(start, target)
+    }
+
+    /*
+    Find the shortest path between two cells that turns as little as
+    possible, starting out in the given heading if it can
+    */
+    pub fn find_heading_path(
+        &self,
+        start: Pos,
+        target: Pos,
+        heading: Direction,
+    ) -> Option<Vec<Pos>> {
+        // Distances to the target, by breadth-first search from it
+        let mut dist = std::collections::HashMap::new();
+        let mut queue = std::collections::VecDeque::from(vec![target]);
+        dist.insert(target, 0u16);
+        while let Some(curr) = queue.pop_front() {
+            if curr == start {
+                break;
+            }
+            let d = dist[&curr];
+            for adj in curr.get_adjacent().into_iter() {
+                if dist.contains_key(&adj) || self.wall_at(adj.r, adj.c) {
+                    continue;
+                }
+                dist.insert(adj, d + 1);
+                queue.push_back(adj);
+            }
+        }
+        let mut remaining = *dist.get(&start)?;
+        if remaining == 0 {
+            return None;
+        }
+
+        // Walk down the distances, going straight whenever possible
+        let mut path = Vec::with_capacity(remaining as usize);
+        let (mut curr, mut dir) = (start, heading);
+        while remaining > 0 {
+            let closer = |d: Direction| {
+                let next = Pos {
+                    r: curr.r + d.get_drow(),
+                    c: curr.c + d.get_dcol(),
+                };
+                (dist.get(&next) == Some(&(remaining - 1))).then_some((next, d))
+            };
+            let (next, next_dir) = closer(dir)
+                .or_else(|| (0..NUM_DIRS).find_map(|idx| closer(Direction::from_index(idx))))?;
+            path.push(next);
+            (curr, dir) = (next, next_dir);
+            remaining -= 1;
+        }
+        Some(path)
+    }
+
+    // Longest path an absolute move is interpolated along (longer ones jump)
+    pub fn max_interpolation_len(&self) -> usize {
+        match &self.rules.interpolation {
+            Some(interpolation) => interpolation.max_len as usize,
+            None => MAX_INTERPOLATION_LEN,
+        }
+    }
+
+    /*
+    Find the shortest path between two cells, avoiding walls (the target
+    itself may be a wall cell, such as the ghost house exit)
+    */
+    pub fn find_path(&self, start: Pos, target: Pos) -> Option<Vec<Pos>> {
+        // Begin breadth-first search
+        let mut queue = std::collections::VecDeque::from(vec![start]);
         let mut parent = std::collections::HashMap::new();
-        parent.insert(
-            start,
-            Pos {r: -1, c: -1,},
-        );
-        // Define target position
-        let target = Pos {
-            r: new_row,
-            c: new_col,
-        };
+        parent.insert(start, Pos { r: -1, c: -1 });
 
         let mut found = false;
         // Keep searching until we have exhausted all options or found it
@@ -445,14 +586,13 @@ impl GameState{
 
             // Find adjacencies/neighbors of current cell
             for adj in curr.get_adjacent().into_iter() {
-
                 // Already searched this one, continue
                 if parent.contains_key(&adj) {
                     continue;
                 }
 
                 // Skip walls
-                if self.wall_at(adj.r, adj.c) {
+                if self.wall_at(adj.r, adj.c) && adj != target {
                     continue;
                 }
 
@@ -469,8 +609,8 @@ impl GameState{
         let mut path: Vec<Pos> = Vec::new();
         let mut last = target;
         loop {
-            let &prev = parent.get(&last).unwrap();
-            
+            let &prev = parent.get(&last)?;
+
             if prev.r == -1 && prev.c == -1 {
                 break;
             }
@@ -486,249 +626,609 @@ impl GameState{
             return None;
         }
         path.reverse();
-            Some(path)
+        Some(path)
     }
 
     // Move Pacman back to its spawn point, if necessary
     pub fn try_respawn_pacman(&mut self) {
-        // Acquire the Pacman control lock, to prevent other Pacman movement
-        let _pacman_lock = self.mu_pacman.lock().unwrap();
-
         // Set Pacman to be in its original state
         if self.pacman_loc.is_empty() && self.get_lives() > 0 {
-            self.pacman_loc.copy_from(Pos::PACMAN_SPAWN_LOC);
+            self.pacman_loc.copy_from(&pacman_spawn_loc());
         }
     }
 
-}
+    /******************************* Ghost Movement *******************************/
 
+    // Frighten all ghosts at once
+    pub fn frighten_all_ghosts(&mut self) {
+        // Reset the ghost respawn combo back to 0
+        self.ghost_combo = 0;
 
+        // Loop over all the ghosts (eyes are already on their way home)
+        for ghost in self.ghosts.iter_mut().filter(|ghost| !ghost.is_eyes()) {
+            /*
+                To frighten a ghost, set its fright steps to a specified value
+                and trap it for one step (to force the direction to reverse)
+            */
+            ghost.set_fright_steps(GHOST_FRIGHT_STEPS);
+            if !ghost.is_trapped() {
+                ghost.set_trapped_steps(1);
+            }
+        }
+    }
 
+    // Reverse all ghosts at once (similar to frighten_all_ghosts)
+    pub fn reverse_all_ghosts(&mut self) {
+        // Loop over all the ghosts (eyes keep following their path home)
+        for ghost in self.ghosts.iter_mut().filter(|ghost| !ghost.is_eyes()) {
+            /*
+                To change the direction a ghost, trap it for one step
+                (to force the direction to reverse)
+            */
+            if !ghost.is_trapped() {
+                ghost.set_trapped_steps(1);
+            }
+        }
+    }
 
+    // Reset all ghosts at once
+    pub fn reset_all_ghosts(&mut self) {
+        // Reset the ghost respawn combo back to 0
+        self.ghost_combo = 0;
 
-/******************************* Ghost Movement *******************************/
+        // Reset each of the ghosts
+        for ghost in self.ghosts.iter_mut() {
+            ghost.reset_to_spawn();
+        }
 
-// Frighten all ghosts at once
-pub fn frighten_all_ghosts(&mut self) {
+        // If no lives are left, set all ghosts to stare at the player, menacingly
+        if self.is_game_over() {
+            for ghost in self.ghosts.iter_mut() {
+                if ghost.color != ORANGE {
+                    ghost.next_loc.update_dir(Direction::None);
+                } else {
+                    // Orange does like making eye contact, unfortunately
+                    ghost.next_loc.update_dir(Direction::Left);
+                }
+            }
+        }
+    }
 
-	// Acquire the ghost control lock, to prevent other ghost movement decisions
-	let _ghosts_lock = self.mu_ghosts.lock().unwrap();
+    // Respawn some ghosts, according to a flag
+    pub fn respawn_ghosts(&mut self, _num_ghost_respawns: usize, ghost_respawn_flag: u8) {
+        // Loop over the ghost colors again, to decide which should respawn
+        for i in 0..self.ghosts.len() {
+            // If the ghost should respawn, do so and increase the score and combo
+            if get_bit(ghost_respawn_flag, self.ghosts[i].color) {
+                // Respawn the ghost (or send it home as eyes, if enabled)
+                if self.rules.ghost_eyes {
+                    self.ghosts[i].become_eyes();
+                } else {
+                    self.ghosts[i].respawn_eaten();
+                }
 
-	// Reset the ghost respawn combo back to 0
-	self.ghost_combo = 0;
+                // Add points corresponding to the current combo length
+                let score_points = COMBO_MULTIPLIER << self.ghost_combo as u16;
+                self.increment_score(score_points);
+
+                // Increment the ghost respawn combo
+                self.ghost_combo += 1;
+                self.emit(GameEvent::GhostEaten {
+                    ghost: self.ghosts[i].color,
+                    points: score_points,
+                    combo: self.ghost_combo,
+                });
+            }
+        }
+    }
 
-	// Loop over all the ghosts
-	for ghost in self.ghosts.iter_mut() {
+    // Update all ghosts at once
+    pub fn update_all_ghosts(&mut self) {
+        // With the speed model, ghosts move in step_ghosts, so only tick fright
+        if self.rules.speed_model.is_some() {
+            for ghost in self.ghosts.iter_mut() {
+                ghost.update_fright();
+            }
+            return;
+        }
 
-		/*
-			To frighten a ghost, set its fright steps to a specified value
-			and trap it for one step (to force the direction to reverse)
-		*/
-		ghost.set_fright_steps(GHOST_FRIGHT_STEPS);
-            if !ghost.is_trapped() {
-                ghost.set_trapped_steps(1);
+        // Loop over the individual ghosts
+        for ghost in self.ghosts.iter_mut() {
+            ghost.update();
+        }
+
+        // Elroy moves faster than the other ghosts, so give it occasional extra steps
+        let red = RED as usize;
+        let elroy_period = ELROY_EXTRA_STEP_PERIODS[self.ghosts[red].get_elroy() as usize] as u64;
+        let num_updates = self.get_curr_ticks() / self.get_update_period() as u64;
+        if elroy_period > 0
+            && num_updates.is_multiple_of(elroy_period)
+            && !self.ghosts[red].is_eyes()
+            && !self.ghosts[red].is_frightened()
+        {
+            // Catch Pacman between the two steps, so Elroy can't pass over Pacman
+            self.check_collisions();
+            self.plan_ghost(red);
+            self.ghosts[red].advance();
+        }
+
+        // Eyes move faster than the other ghosts, so give them extra steps
+        for idx in 0..self.ghosts.len() {
+            for _ in 1..GHOST_EYES_STEPS {
+                if !self.ghosts[idx].is_eyes() {
+                    break;
+                }
+                self.plan_ghost(idx);
+                self.ghosts[idx].advance();
             }
-	}
-}
+        }
+    }
+
+    // A game state function to plan all ghosts at once
+    pub fn plan_all_ghosts(&mut self) {
+        // With the speed model, each ghost plans right after it moves
+        if self.rules.speed_model.is_some() {
+            return;
+        }
 
-// Reverse all ghosts at once (similar to frightenAllGhosts)
-pub fn reverse_all_ghosts(&mut self) {
+        // Plan each ghost's next move
+        for i in 0..self.ghosts.len() {
+            self.plan_ghost(i);
+        }
+    }
+
+    /******************** Ghost Planning (after serialization) ********************/
 
-	// Loop over all the ghosts
-	for ghost in self.ghosts.iter_mut() {
+    // Plan a ghost's next move
+    pub fn plan_ghost(&mut self, idx: usize) {
+        // If the location is empty (i.e. after a reset/respawn), don't plan
+        if self.ghosts[idx].loc.is_empty() {
+            return;
+        }
 
-		/*
-			To change the direction a ghost, trap it for one step
-			(to force the direction to reverse)
-		*/
-		if !ghost.is_trapped() {
-            ghost.set_trapped_steps(1);
+        // Eyes head straight back to the ghost house instead
+        if self.ghosts[idx].is_eyes() {
+            self.plan_eyes(idx);
+            return;
         }
-	}
-}
 
-// Reset all ghosts at once
-pub fn reset_all_ghosts(&mut self) {
+        // Determine the next position based on the current direction
+        let loc = self.ghosts[idx].loc.clone();
+        self.ghosts[idx].next_loc.advance_from(&loc);
 
-	// Acquire the ghost control lock, to prevent other ghost movement
-	let _ghosts_lock = self.mu_ghosts.lock().unwrap();
+        // If the ghost is trapped, reverse the current direction and return
+        if self.ghosts[idx].is_trapped() {
+            let reversed = self.ghosts[idx].next_loc.get_reversed_dir();
+            self.ghosts[idx].next_loc.update_dir(reversed);
+            self.ghosts[idx].dec_trapped_steps();
+            return;
+        }
+
+        // If the ghost is waiting for its release, keep bouncing in the house
+        if self.ghost_waiting(idx) {
+            let reversed = self.ghosts[idx].next_loc.get_reversed_dir();
+            self.ghosts[idx].next_loc.update_dir(reversed);
+            return;
+        }
 
-	// Reset the ghost respawn combo back to 0
-	self.ghost_combo = 0;
+        // Keep local copies of the fright steps and spawning variables
+        let fright_steps = self.ghosts[idx].get_fright_steps();
+        let spawning = self.ghosts[idx].is_spawning();
+        let color = self.ghosts[idx].color;
+        let next_loc = self.ghosts[idx].next_loc.clone();
 
-    
-	// Reset each of the ghosts
-	// let mut handles = vec![];
-    for ghost in self.ghosts.iter() {
-        // let handle = thread::spawn(move || {
-            ghost.reset();
-    //    });
-    //    handles.push(handle);
-    }
-	// Wait for the resets to finish
-	// for handle in handles {
-    //     handle.join().expect("Ghost reset thread panicked");
-    // }
+        // Capture the last unpaused current game mode (could be the current mode)
+        let mode = self.get_last_unpaused_mode();
 
-	// If no lives are left, set all ghosts to stare at the player, menacingly
-	if self.get_lives() == 0 {
-        for ghost in self.ghosts.iter_mut() {
-            if ghost.color != ORANGE {
-                ghost.next_loc.update_dir(NONE);
+        /*
+            If the ghost is spawning in the ghost house, choose red's spawn
+            location as the target to encourage it to leave the ghost house
+
+            Otherwise: pick chase or scatter targets, depending on the mode
+        */
+        let red_spawn = ghost_spawn_locs()[RED as usize].clone();
+        let (target_row, target_col) = if spawning
+            && !loc.collides_with(&red_spawn)
+            && !next_loc.collides_with(&red_spawn)
+        {
+            red_spawn.get_coords()
+        } else if mode == CHASE || self.ghosts[idx].is_elroy() {
+            // Elroy keeps chasing, even in scatter mode
+            self.get_nearest_chase_target(idx)
+        } else if mode == SCATTER {
+            self.ghosts[idx].scatter_target.get_coords()
+        } else {
+            (0, 0)
+        };
+
+        /*
+            Determine whether each of the four neighboring moves to the next
+            location is valid, and count how many are good
+        */
+        let mut num_valid_moves = 0;
+        let mut move_valid = [false; NUM_DIRS as usize];
+        let mut move_dist_sq = [0; NUM_DIRS as usize];
+        for dir_idx in 0..NUM_DIRS {
+            let dir = Direction::from_index(dir_idx);
+            let d = dir_idx as usize;
+
+            // Get the neighboring cell in that location
+            let (row, col) = next_loc.get_neighbor_coords(dir);
+
+            // Calculate the distance from the target to the move location
+            // (scored by the ghost AI settings, once out of the ghost house)
+            move_dist_sq[d] = if spawning {
+                self.dist_sq(row, col, target_row, target_col)
             } else {
-                // Orange does like making eye contact, unfortunately
-                ghost.next_loc.update_dir(LEFT);
+                self.ghost_move_score(idx, &next_loc, dir, (target_row, target_col))
+            };
+
+            // Determine if that move is valid
+            move_valid[d] = !self.wall_at(row, col);
+
+            // Considerations when the ghost is spawning
+            if spawning {
+                // Determine if the move would be within the ghost house
+                if self.ghost_spawn_at(row, col) {
+                    move_valid[d] = true;
+                }
+
+                /*
+                    Determine if the move would help the ghost escape the ghost house,
+                    and make it a valid one if so
+                */
+                if row == GHOST_HOUSE_EXIT_ROW && col == GHOST_HOUSE_EXIT_COL {
+                    move_valid[d] = true;
+                }
+            }
+
+            // If this move would make the ghost reverse, skip it
+            if dir == next_loc.get_reversed_dir() {
+                move_valid[d] = false;
+            }
+
+            // Increment the valid moves counter if necessary
+            if move_valid[d] {
+                num_valid_moves += 1;
             }
         }
-    }
-}
 
-// Respawn some ghosts, according to a flag
-pub fn respawn_ghosts(&mut self, _num_ghost_respawns: usize, ghost_respawn_flag: u8) {
+        // Debug statement, in case a ghost somehow is surrounded by all walls
+        if num_valid_moves == 0 {
+            let (row, col) = next_loc.get_coords();
+            warn!(
+                "WARN: {} has nowhere to go (row = {}, col = {}, dir = {}, spawning = {})",
+                GHOST_NAMES[color as usize],
+                row,
+                col,
+                next_loc.dir,
+                spawning
+            );
+            return;
+        }
 
-	// Acquire the ghost control lock, to prevent other ghost movement
-	
-    // let mut handles = vec![];
-    let _ghosts_lock = self.mu_ghosts.lock().unwrap();
+        // A controlled ghost takes its controller's direction, if it is legal
+        if let Some(dir) = self.controlled_ghost_dir(idx) {
+            if !spawning && move_valid[dir.to_index() as usize] {
+                self.ghosts[idx].next_loc.update_dir(dir);
+                return;
+            }
+        }
 
-	// Loop over the ghost colors again, to decide which should respawn
-	for i in 0..self.ghosts.len() {
-        let ghost = &self.ghosts[i];
+        /*
+            If the ghost will still frightened one tick later, immediately choose
+            a random valid direction and return
+        */
+        if fright_steps > 1 {
+            let dir = self.random_ghost_move(&move_valid, num_valid_moves);
+            self.ghosts[idx].next_loc.update_dir(dir);
+            return;
+        }
 
-		// If the ghost should respawn, do so and increase the score and combo
-		if get_bit(ghost_respawn_flag, ghost.color) {
-            // Respawn the ghost
-            let ghost_respawn_clone = thread::spawn(move || {
-                ghost.respawn();
-            });
-            handles.push(ghost_respawn_clone);
+        // Depending on the ghost AI settings, turn at random now and then
+        if !spawning && num_valid_moves > 1 && self.ghost_turns_randomly() {
+            let dir = self.random_ghost_move(&move_valid, num_valid_moves);
+            self.ghosts[idx].next_loc.update_dir(dir);
+            return;
+        }
 
-			// Add points corresponding to the current combo length
-			let score_points =
-                COMBO_MULTIPLIER as i32 * (1 << self.ghost_combo as u16) as i32;
-            self.increment_score(score_points);
+        // Otherwise, choose the best direction to reach the target
+        let mut best_dir = Direction::Up;
+        let mut best_dist = i32::MAX; // Some arbitrarily high number
+        for dir_idx in 0..NUM_DIRS {
+            let d = dir_idx as usize;
 
-			// Increment the ghost respawn combo
-			self.ghost_combo += 1;
-		}
-	}
+            // Skip any invalid moves
+            if !move_valid[d] {
+                continue;
+            }
+
+            // Compare this direction to the best so far
+            if move_dist_sq[d] < best_dist {
+                best_dir = Direction::from_index(dir_idx);
+                best_dist = move_dist_sq[d];
+            }
+        }
 
-	// Wait for the respawns to finish
-	for handle in handles {
-        handle.join().expect("Ghost respawn thread panicked");
+        // Once we have picked the best direction, update it
+        self.ghosts[idx].next_loc.update_dir(best_dir);
     }
-}
 
-// Update all ghosts at once
-pub fn update_all_ghosts(&mut self) {
+    // Plan the next move of a ghost returning to the ghost house as eyes
+    fn plan_eyes(&mut self, idx: usize) {
+        let exit = Pos {
+            r: GHOST_HOUSE_EXIT_ROW,
+            c: GHOST_HOUSE_EXIT_COL,
+        };
+        let (row, col) = self.ghosts[idx].loc.get_coords();
 
-	// Acquire the ghost control lock, to prevent other ghost movement
-	let _ghosts_lock = self.mu_ghosts.lock().unwrap();
-    let mut handles = vec![];
+        // Once the eyes reach the exit (or can't find it), re-enter the house
+        if (row == exit.r && col == exit.c) || self.ghost_spawn_at(row, col) {
+            self.ghosts[idx].respawn_eaten();
+            return;
+        }
 
-	// Loop over the individual ghosts
-	for ghost in self.ghosts.iter() {
-        let handle = thread::spawn(move || {
-            ghost.update();
-        });
-        handles.push(handle);
+        // Determine the next position based on the current direction
+        let loc = self.ghosts[idx].loc.clone();
+        self.ghosts[idx].next_loc.advance_from(&loc);
+        let (next_row, next_col) = self.ghosts[idx].next_loc.get_coords();
+
+        // Follow the shortest path from the next position to the exit
+        let start = Pos {
+            r: next_row,
+            c: next_col,
+        };
+        let dir = match self.find_path(start, exit) {
+            Some(path) => {
+                let step = path[0];
+                (0..NUM_DIRS)
+                    .map(Direction::from_index)
+                    .find(|dir| {
+                        (next_row + dir.get_drow(), next_col + dir.get_dcol()) == (step.r, step.c)
+                    })
+                    .unwrap_or(Direction::None)
+            }
+            None if start == exit => Direction::Down,
+            None => {
+                self.ghosts[idx].respawn_eaten();
+                return;
+            }
+        };
+        self.ghosts[idx].next_loc.update_dir(dir);
     }
 
-	// Wait for the respawns to finish
-	for handle in handles {
-        handle.join().expect("Ghost update thread panicked");
+    /******************************** Cruise Elroy ********************************/
+
+    // Pellets left when red enters Elroy 1 at the current level (Elroy 2 is half)
+    pub fn elroy_threshold(&self) -> u16 {
+        ELROY_THRESHOLDS
+            .iter()
+            .take_while(|band| band[0] <= self.get_level() as u16)
+            .last()
+            .map_or(ELROY_THRESHOLDS[0][1], |band| band[1])
     }
-}
 
-// A game state function to plan all ghosts at once
-pub fn plan_all_ghosts(&mut self) {
+    // Update red's Elroy level from the number of pellets left
+    pub fn update_elroy(&mut self) {
+        let threshold = self.elroy_threshold();
+        let num_pellets = self.get_num_pellets();
+        let elroy = if num_pellets <= threshold / 2 {
+            2
+        } else if num_pellets <= threshold {
+            1
+        } else {
+            0
+        };
 
-	// Acquire the ghost control lock, to prevent other ghost movement
-	let _ghosts_lock = self.mu_ghosts.lock().unwrap();
+        let red = &mut self.ghosts[RED as usize];
+        if red.get_elroy() != elroy {
+            info!(
+                "GAME: Red Elroy level changed ({} -> {}) (t = {})",
+                red.get_elroy(),
+                elroy,
+                self.curr_ticks
+            );
+            red.set_elroy(elroy);
+            self.emit(GameEvent::ElroyChanged { elroy });
+        }
+    }
 
-    let mut handles = vec![];
+    /************************ Ghost Targeting (Chase Mode) ************************/
 
-	// Plan each ghost's next move concurrently
-	for ghost in self.ghosts.iter() {
-        let handle = thread::spawn(move || {
-            ghost.plan();
-        });
-        handles.push(handle);
+    /*
+    Returns the chase location of the red ghost
+    (i.e. Pacman's exact location)
+    */
+    pub fn get_chase_target_red(&self) -> (i8, i8) {
+        // Return Pacman's current location
+        self.pacman_loc.get_coords()
     }
 
-	// Wait until all pending ghost plans are complete
-	for handle in handles {
-        handle.join().expect("Ghost plan thread panicked");
+    /*
+    Returns the chase location of the pink ghost
+    (i.e. 4 spaces ahead of Pacman's location)
+    */
+    pub fn get_chase_target_pink(&self) -> (i8, i8) {
+        // Return the red pink's target (4 spaces ahead of Pacman)
+        self.pacman_loc.get_ahead_coords(4)
     }
-}
 
-/************************ Ghost Targeting (Chase Mode) ************************/
+    /*
+    Returns the chase location of the cyan ghost
+    (i.e. The red ghost's location, reflected about 2 spaces ahead of Pacman)
+    */
+    pub fn get_chase_target_cyan(&self) -> (i8, i8) {
+        // Get the 'pivot' square, 2 steps ahead of Pacman
+        let (pivot_row, pivot_col) = self.pacman_loc.get_ahead_coords(2);
+
+        // Get the current location of the red ghost
+        let (red_row, red_col) = self.ghosts[RED as usize].loc.get_coords();
+
+        // Return the pair of coordinates of the calculated target
+        (
+            pivot_row.wrapping_mul(2).wrapping_sub(red_row),
+            pivot_col.wrapping_mul(2).wrapping_sub(red_col),
+        )
+    }
 
-/*
-Returns the chase location of the red ghost
-(i.e. Pacman's exact location)
-*/
-pub fn get_chase_target_red(&self) -> (i8, i8) {
+    /*
+    Returns the chase location of the orange ghost
+    (i.e. Pacman's exact location, the same as red's target most of the time)
+    Though, if close enough to Pacman, it should choose its scatter target
+    */
+    pub fn get_chase_target_orange(&self) -> (i8, i8) {
+        // Get Pacman's current location
+        let (pacman_row, pacman_col) = self.pacman_loc.get_coords();
 
-	// Return Pacman's current location
-	self.pacman_loc.get_coords()
-}
+        // Get the orange ghost's current location
+        let (orange_row, orange_col) = self.ghosts[ORANGE as usize].loc.get_coords();
 
-/*
-Returns the chase location of the pink ghost
-(i.e. 4 spaces ahead of Pacman's location)
-*/
-pub fn get_chase_target_pink(&self) -> (i8, i8) {
+        // If Pacman is far enough from the ghost, return Pacman's location
+        if self.dist_sq(orange_row, orange_col, pacman_row, pacman_col) >= 64 {
+            return (pacman_row, pacman_col);
+        }
+
+        // Otherwise, return the scatter location of orange
+        self.ghosts[ORANGE as usize].scatter_target.get_coords()
+    }
 
-	// Return the red pink's target (4 spaces ahead of Pacman)
-	self.pacman_loc.get_ahead_coords(4)
+    // Returns the chase location of an arbitrary ghost color
+    pub fn get_chase_target(&self, color: u8) -> (i8, i8) {
+        match color {
+            RED => self.get_chase_target_red(),
+            PINK => self.get_chase_target_pink(),
+            CYAN => self.get_chase_target_cyan(),
+            ORANGE => self.get_chase_target_orange(),
+            _ => empty_loc().get_coords(),
+        }
+    }
 }
 
-/*
-Returns the chase location of the cyan ghost
-(i.e. The red ghost's location, reflected about 2 spaces ahead of Pacman)
-*/
-pub fn get_chase_target_cyan(&self) -> (i8, i8) {
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::GameRules;
 
-	// Get the 'pivot' square, 2 steps ahead of Pacman
-	let (pivot_row, pivot_col) = self.pacman_loc.get_ahead_coords(2);
+    // A game where red has wandered a few cells from the ghost house exit
+    fn game_with_red_out(ghost_eyes: bool) -> GameState {
+        let mut gs = GameState::with_rules(GameRules {
+            ghost_eyes,
+            ..Default::default()
+        });
+        gs.play();
+        while gs.ghosts[RED as usize].is_spawning() || red_dist_to_exit(&gs) < 5 {
+            gs.step_ticks(1);
+        }
+        gs
+    }
 
-	// Get the current location of the red ghost
-	let (red_row, red_col) = self.ghosts[RED as usize].loc.get_coords();
+    // Length of red's shortest path to the ghost house exit
+    fn red_dist_to_exit(gs: &GameState) -> usize {
+        let (r, c) = gs.ghosts[RED as usize].loc.get_coords();
+        let exit = Pos {
+            r: GHOST_HOUSE_EXIT_ROW,
+            c: GHOST_HOUSE_EXIT_COL,
+        };
+        gs.find_path(Pos { r, c }, exit)
+            .map_or(0, |path| path.len())
+    }
 
-	// Return the pair of coordinates of the calculated target
-	(2*pivot_row - red_row), (2*pivot_col - red_col)
-}
+    #[test]
+    fn eaten_ghost_teleports_without_eyes() {
+        let mut gs = game_with_red_out(false);
+        gs.respawn_ghosts(1, 1 << RED);
+        let red = &gs.ghosts[RED as usize];
+        assert!(!red.is_eyes());
+        assert!(red.loc.is_empty());
+        assert!(red.is_spawning() && red.is_eaten());
+    }
 
-/*
-Returns the chase location of the orange ghost
-(i.e. Pacman's exact location, the same as red's target most of the time)
-Though, if close enough to Pacman, it should choose its scatter target
-*/
-pub fn get_chase_target_orange(&self) -> (i8, i8) {
+    #[test]
+    fn eyes_travel_back_to_the_house() {
+        let mut gs = game_with_red_out(true);
+        gs.respawn_ghosts(1, 1 << RED);
+        let red = RED as usize;
+        assert!(gs.ghosts[red].is_eyes());
+        assert!(!gs.ghosts[red].is_frightened());
+        assert!(!gs.ghosts[red].loc.is_empty());
+        assert_eq!(gs.curr_score, COMBO_MULTIPLIER as u32);
+
+        // After the move already planned, the eyes close in on the exit
+        // at every update, then re-enter the house
+        let period = gs.get_update_period() as u16;
+        gs.step_ticks(period);
+        let mut dist = red_dist_to_exit(&gs);
+        let mut ticks = 0;
+        while gs.ghosts[red].is_eyes() {
+            gs.step_ticks(1);
+            if gs.ghosts[red].is_eyes() {
+                let next_dist = red_dist_to_exit(&gs);
+                assert!(next_dist <= dist);
+                dist = next_dist;
+            }
+            ticks += 1;
+            assert!(ticks < 40 * period);
+        }
 
-	// Get Pacman's current location
-	let (pacman_row, pacman_col) = self.pacman_loc.get_coords();
+        // Back in the house (at pink's spawn, for red), on its way out again
+        let (row, col) = gs.ghosts[red].loc.get_coords();
+        assert!(gs.ghost_spawn_at(row, col));
+        assert!(gs.ghosts[red].is_spawning());
+        assert_eq!((row, col), ghost_spawn_locs()[PINK as usize].get_coords());
+    }
 
-	// Get the orange ghost's current location
-	let (orange_row, orange_col) = self.ghosts[ORANGE as usize].loc.get_coords();
+    #[test]
+    fn elroy_thresholds_follow_the_level() {
+        let mut gs = GameState::new();
+        for (level, threshold) in [(1, 20), (2, 30), (5, 40), (6, 50), (18, 100), (30, 120)] {
+            gs.set_level(level);
+            assert_eq!(gs.elroy_threshold(), threshold);
+        }
+    }
 
-	// If Pacman is far enough from the ghost, return Pacman's location
-	if self.dist_sq(orange_row, orange_col, pacman_row, pacman_col) >= 64 {
-        return (pacman_row, pacman_col)
+    #[test]
+    fn elroy_level_follows_the_pellets_left() {
+        let mut gs = GameState::with_rules(GameRules {
+            anger_model: AngerModel::Elroy,
+            ..Default::default()
+        });
+        let red = RED as usize;
+        for (pellets, elroy) in [(21, 0), (20, 1), (11, 1), (10, 2), (1, 2)] {
+            gs.num_pellets = pellets;
+            gs.update_elroy();
+            assert_eq!(gs.ghosts[red].get_elroy(), elroy);
+        }
+        assert!(gs
+            .events
+            .iter()
+            .any(|record| record.event == GameEvent::ElroyChanged { elroy: 2 }));
     }
 
-	// Otherwise, return the scatter location of orange
-	self.ghosts[ORANGE as usize].scatter_target.get_coords()
-}
+    // Cells red moves over some updates, at an Elroy level
+    fn red_cells_moved(elroy: u8, updates: u16) -> i8 {
+        let mut gs = GameState::new();
+        gs.play();
+        let red = RED as usize;
+        let period = gs.get_update_period() as u16;
+        while gs.ghosts[red].is_spawning() || !gs.curr_ticks.is_multiple_of(period as u64) {
+            gs.step_ticks(1);
+        }
+        gs.ghosts[red].set_elroy(elroy);
+
+        let mut cells = 0;
+        for _ in 0..updates {
+            let (row, col) = gs.ghosts[red].loc.get_coords();
+            gs.step_ticks(period);
+            let (next_row, next_col) = gs.ghosts[red].loc.get_coords();
+            cells += (next_row - row).abs() + (next_col - col).abs();
+        }
+        cells
+    }
 
-// Returns the chase location of an arbitrary ghost color
-pub fn get_chase_target(&self, color: u8) -> (i8, i8) {
-	match color {
-        RED => self.get_chase_target_red(),
-        PINK => self.get_chase_target_pink(),
-        CYAN => self.get_chase_target_cyan(),
-        ORANGE => self.get_chase_target_orange(),
-        _ => Pos::EMPTY_LOC.get_coords(),
+    #[test]
+    fn elroy_takes_extra_steps() {
+        assert_eq!(red_cells_moved(0, 8), 8);
+        assert_eq!(red_cells_moved(1, 8), 9);
+        assert_eq!(red_cells_moved(2, 8), 10);
     }
 }
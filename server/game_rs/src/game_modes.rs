@@ -3,6 +3,7 @@
 use tracing::info;
 
 use crate::constants::*;
+use crate::events::GameEvent;
 use crate::state::GameState;
 
 // Mode name strings for logging
@@ -23,6 +24,10 @@ impl GameState {
                 MODE_NAMES[mode as usize],
                 self.curr_ticks
             );
+            self.emit(GameEvent::ModeChanged {
+                from: self.mode,
+                to: mode,
+            });
         }
         self.mode = mode;
     }
@@ -46,6 +51,10 @@ impl GameState {
                 MODE_NAMES[mode as usize],
                 self.curr_ticks
             );
+            self.emit(GameEvent::ModeChanged {
+                from: unpaused_mode,
+                to: mode,
+            });
         }
         self.last_unpaused_mode = mode;
     }
@@ -63,14 +72,16 @@ impl GameState {
         self.last_unpaused_mode = self.mode;
         self.mode = PAUSED;
         info!("GAME: Paused (t = {})", self.curr_ticks);
+        self.emit(GameEvent::Paused);
     }
 
     pub fn play(&mut self) {
-        if !self.is_paused() || self.curr_lives == 0 || self.curr_ticks == 0xffff {
+        if !self.is_paused() || self.is_game_over() {
             return;
         }
         self.mode = self.last_unpaused_mode;
         info!("GAME: Resumed (t = {})", self.curr_ticks);
+        self.emit(GameEvent::Resumed);
     }
 
     /************************* Pause on Next Update *************************/
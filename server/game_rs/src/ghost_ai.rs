@@ -0,0 +1,301 @@
+// ghost_ai.rs - Ghost AI difficulty settings
+//
+// Outside of frightened mode, the arcade ghosts are fully deterministic. For
+// training and testing bots, the ghosts can instead make occasional random
+// turns (easier), or look further ahead and spread out as a pack (harder).
+// Any randomness is drawn from the game RNG, so seeded games stay repeatable.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::direction::{Direction, NUM_DIRS};
+use crate::location::LocationState;
+use crate::state::GameState;
+
+// Extra (squared) distance for a move onto a cell another ghost holds or is
+// heading to, when the ghosts coordinate as a pack
+const PACK_PENALTY: i32 = 64;
+
+// Deepest lookahead allowed (each cell ahead triples the moves considered)
+pub const MAX_LOOKAHEAD: u8 = 6;
+
+/// Named ghost AI settings
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    /// The deterministic arcade ghosts
+    #[default]
+    Arcade,
+    Hard,
+    Expert,
+}
+
+impl Difficulty {
+    /// Ghost AI settings of this preset (None for the arcade ghosts)
+    pub fn ghost_ai(&self) -> Option<GhostAi> {
+        let (epsilon, lookahead, pack) = match self {
+            Difficulty::Easy => (0.3, 1, false),
+            Difficulty::Normal => (0.1, 1, false),
+            Difficulty::Arcade => return None,
+            Difficulty::Hard => (0.0, 3, false),
+            Difficulty::Expert => (0.0, 4, true),
+        };
+        Some(GhostAi {
+            epsilon,
+            lookahead,
+            pack,
+        })
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Arcade => "arcade",
+            Difficulty::Hard => "hard",
+            Difficulty::Expert => "expert",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "arcade" => Ok(Difficulty::Arcade),
+            "hard" => Ok(Difficulty::Hard),
+            "expert" => Ok(Difficulty::Expert),
+            _ => Err(format!("unknown difficulty: {}", s)),
+        }
+    }
+}
+
+/// How the ghosts choose their moves in chase and scatter modes
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct GhostAi {
+    /// Chance of a random turn at each decision (0 - 1)
+    pub epsilon: f32,
+    /// Cells looked ahead when comparing moves (1 is the arcade behavior,
+    /// at most MAX_LOOKAHEAD)
+    pub lookahead: u8,
+    /// Ghosts avoid cells other ghosts hold or are heading to
+    pub pack: bool,
+}
+
+impl Default for GhostAi {
+    fn default() -> Self {
+        Self {
+            epsilon: 0.0,
+            lookahead: 1,
+            pack: false,
+        }
+    }
+}
+
+impl GameState {
+    // Pick one of the valid moves at random (using the game RNG)
+    pub(crate) fn random_ghost_move(
+        &mut self,
+        move_valid: &[bool; NUM_DIRS as usize],
+        num_valid_moves: usize,
+    ) -> Direction {
+        // Generate a random index out of the valid moves
+        let random_num = self.rng.gen_range(0..num_valid_moves);
+
+        // Loop over all valid directions, stopping at the chosen one
+        let dir_idx = (0..NUM_DIRS)
+            .filter(|&d| move_valid[d as usize])
+            .nth(random_num)
+            .unwrap_or(0);
+        Direction::from_index(dir_idx)
+    }
+
+    // Roll for a random turn, under the ghost AI settings
+    pub(crate) fn ghost_turns_randomly(&mut self) -> bool {
+        let epsilon = match &self.rules.ghost_ai {
+            Some(ai) => ai.epsilon,
+            None => return false,
+        };
+        epsilon > 0.0 && self.rng.gen::<f32>() < epsilon
+    }
+
+    /*
+    Score of a ghost's move from a cell towards a target (lower is better):
+    the squared distance to the target, after looking ahead if enabled, plus
+    a penalty for crowding another ghost if the pack coordinates
+    */
+    pub(crate) fn ghost_move_score(
+        &self,
+        idx: usize,
+        from: &LocationState,
+        dir: Direction,
+        target: (i8, i8),
+    ) -> i32 {
+        let (row, col) = from.get_neighbor_coords(dir);
+        let Some(ai) = &self.rules.ghost_ai else {
+            return self.dist_sq(row, col, target.0, target.1);
+        };
+
+        // Look further ahead: a move is as good as the best cell it leads to
+        let depth = ai.lookahead.clamp(1, MAX_LOOKAHEAD) - 1;
+        let mut score = self.best_dist_ahead(&LocationState::new(row, col, dir), depth, target);
+
+        // Spread the pack over different routes
+        if ai.pack {
+            let crowded = self.ghosts.iter().enumerate().any(|(other, ghost)| {
+                other != idx
+                    && !ghost.is_eyes()
+                    && !ghost.loc.is_empty()
+                    && (ghost.loc.get_coords() == (row, col)
+                        || ghost.next_loc.get_coords() == (row, col))
+            });
+            if crowded {
+                score += PACK_PENALTY;
+            }
+        }
+        score
+    }
+
+    // Smallest squared distance to the target reachable within some number of
+    // (non-reversing) moves from a location, facing its direction
+    fn best_dist_ahead(&self, loc: &LocationState, depth: u8, target: (i8, i8)) -> i32 {
+        let (row, col) = loc.get_coords();
+        let here = self.dist_sq(row, col, target.0, target.1);
+        if depth == 0 {
+            return here;
+        }
+        (0..NUM_DIRS)
+            .map(Direction::from_index)
+            .filter(|&dir| dir != loc.dir.reverse())
+            .filter_map(|dir| {
+                let (next_row, next_col) = loc.get_neighbor_coords(dir);
+                if self.wall_at(next_row, next_col) {
+                    return None;
+                }
+                let next = LocationState::new(next_row, next_col, dir);
+                Some(self.best_dist_ahead(&next, depth - 1, target))
+            })
+            .fold(here, i32::min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::GameRules;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn game_with_ai(ai: GhostAi) -> GameState {
+        GameState::with_rules(GameRules {
+            ghost_ai: Some(ai),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn presets_are_named() {
+        for difficulty in [
+            Difficulty::Easy,
+            Difficulty::Normal,
+            Difficulty::Arcade,
+            Difficulty::Hard,
+            Difficulty::Expert,
+        ] {
+            assert_eq!(difficulty.to_string().parse(), Ok(difficulty));
+        }
+        assert_eq!("Hard".parse(), Ok(Difficulty::Hard));
+        assert!("impossible".parse::<Difficulty>().is_err());
+    }
+
+    #[test]
+    fn presets_set_the_ghost_ai() {
+        let mut rules = GameRules::default();
+        rules.set_difficulty(Difficulty::Easy);
+        assert_eq!(rules.ghost_ai.as_ref().map(|ai| ai.epsilon), Some(0.3));
+        rules.set_difficulty(Difficulty::Expert);
+        assert_eq!(
+            rules.ghost_ai,
+            Some(GhostAi {
+                epsilon: 0.0,
+                lookahead: 4,
+                pack: true,
+            })
+        );
+        rules.set_difficulty(Difficulty::Arcade);
+        assert_eq!(rules.ghost_ai, None);
+    }
+
+    #[test]
+    fn random_turns_follow_epsilon() {
+        let mut gs = GameState::new();
+        assert!(!(0..100).any(|_| gs.ghost_turns_randomly()));
+
+        let mut gs = game_with_ai(GhostAi::default());
+        assert!(!(0..100).any(|_| gs.ghost_turns_randomly()));
+
+        let mut gs = game_with_ai(GhostAi {
+            epsilon: 1.0,
+            ..Default::default()
+        });
+        assert!((0..100).all(|_| gs.ghost_turns_randomly()));
+    }
+
+    #[test]
+    fn lookahead_scores_the_best_cell_ahead() {
+        // Right from (23, 13), (21, 15) is 5 away, but (22, 15) is 2 moves on
+        let from = LocationState::new(23, 13, Direction::Right);
+        let gs = GameState::new();
+        assert_eq!(gs.ghost_move_score(0, &from, Direction::Right, (21, 15)), 5);
+
+        let gs = game_with_ai(GhostAi {
+            lookahead: 3,
+            ..Default::default()
+        });
+        assert_eq!(gs.ghost_move_score(0, &from, Direction::Right, (21, 15)), 1);
+    }
+
+    #[test]
+    fn pack_avoids_crowding_other_ghosts() {
+        let mut gs = game_with_ai(GhostAi {
+            pack: true,
+            ..Default::default()
+        });
+        let from = LocationState::new(23, 13, Direction::Right);
+        gs.ghosts[1].loc.update_coords(23, 14);
+
+        // Only the other ghosts count
+        assert_eq!(
+            gs.ghost_move_score(0, &from, Direction::Right, (21, 15)),
+            5 + PACK_PENALTY
+        );
+        assert_eq!(gs.ghost_move_score(1, &from, Direction::Right, (21, 15)), 5);
+    }
+
+    #[test]
+    fn seeded_random_ghosts_repeat() {
+        let run = || {
+            let mut gs = GameState::with_rules(GameRules {
+                ghost_ai: Difficulty::Easy.ghost_ai(),
+                ..Default::default()
+            });
+            gs.rng = StdRng::seed_from_u64(7);
+            gs.play();
+            gs.step_ticks(600);
+            gs.ghosts.map(|ghost| ghost.loc.get_coords())
+        };
+        assert_eq!(run(), run());
+    }
+}
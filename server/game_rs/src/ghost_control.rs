@@ -0,0 +1,205 @@
+// ghost_control.rs - Ghosts steered by external controllers
+//
+// Any ghost can be taken over by a client (a bot, or a human with a gamepad),
+// which then sends the direction the ghost should take. Directions follow the
+// same rules as the AI's (no walls, no reversing), and if the controller goes
+// quiet for too long, the AI drives the ghost again until it hears back.
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::constants::*;
+use crate::direction::Direction;
+use crate::game_helpers::MoveOutcome;
+use crate::state::GameState;
+
+/// Settings for externally controlled ghosts
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct GhostControl {
+    /// Ticks without a command before the AI steers the ghost again
+    pub timeout: u16,
+}
+
+impl Default for GhostControl {
+    fn default() -> Self {
+        Self { timeout: 48 }
+    }
+}
+
+/// The client steering a ghost, and the direction it last asked for
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct GhostController {
+    pub client: u32,
+    pub dir: Direction,
+    pub last_tick: u64,
+}
+
+impl GameState {
+    /// Whether a client has steered a ghost recently enough to be in control
+    pub fn ghost_controller_active(&self, idx: usize) -> bool {
+        match &self.ghosts[idx].controller {
+            Some(controller) => {
+                let timeout = self.rules.ghost_control.timeout as u64;
+                self.curr_ticks.saturating_sub(controller.last_tick) <= timeout
+            }
+            None => false,
+        }
+    }
+
+    /// Whether a client may steer a ghost (it controls it, or nobody does)
+    pub fn may_control_ghost(&self, idx: usize, client: u32) -> bool {
+        match &self.ghosts[idx].controller {
+            Some(controller) => controller.client == client || !self.ghost_controller_active(idx),
+            None => true,
+        }
+    }
+
+    /// Hand a ghost over to a client; returns false if another client is
+    /// still in control of it
+    pub fn take_ghost_control(&mut self, idx: usize, client: u32) -> bool {
+        if !self.may_control_ghost(idx, client) {
+            return false;
+        }
+        info!(
+            "GAME: Client {} took control of {} (t = {})",
+            client, GHOST_NAMES[idx], self.curr_ticks
+        );
+        self.ghosts[idx].controller = Some(GhostController {
+            client,
+            dir: Direction::None,
+            last_tick: self.curr_ticks,
+        });
+        true
+    }
+
+    /// Give a ghost back to the AI; returns false if the client wasn't the
+    /// one in control of it
+    pub fn release_ghost_control(&mut self, idx: usize, client: u32) -> bool {
+        if !self.may_control_ghost(idx, client) {
+            return false;
+        }
+        if self.ghosts[idx].controller.take().is_some() {
+            info!(
+                "GAME: Client {} released {} (t = {})",
+                client, GHOST_NAMES[idx], self.curr_ticks
+            );
+        }
+        true
+    }
+
+    // Whether a ghost is moving freely through the maze (so its plan is
+    // just the direction to take at its next cell)
+    fn ghost_roaming(&self, idx: usize) -> bool {
+        let ghost = &self.ghosts[idx];
+        !ghost.loc.is_empty()
+            && !ghost.is_eyes()
+            && !ghost.is_trapped()
+            && !ghost.is_spawning()
+            && !self.ghost_waiting(idx)
+    }
+
+    /*
+    Steer a ghost (taking control of it if needed): the direction is taken at
+    the ghost's next cell, and at every cell after that where it is legal,
+    until the next command; returns None if another client is in control, and
+    Buffered if the ghost is not yet roaming (the direction is kept for later)
+    */
+    pub fn steer_ghost(&mut self, idx: usize, client: u32, dir: Direction) -> Option<MoveOutcome> {
+        if !self.may_control_ghost(idx, client) {
+            return None;
+        }
+        if self.is_paused() || self.get_pause_on_update() {
+            return Some(MoveOutcome::Paused);
+        }
+        if self.ghosts[idx]
+            .controller
+            .is_none_or(|controller| controller.client != client)
+        {
+            self.take_ghost_control(idx, client);
+        }
+        let last_tick = self.curr_ticks;
+        if let Some(controller) = self.ghosts[idx].controller.as_mut() {
+            controller.dir = dir;
+            controller.last_tick = last_tick;
+        }
+
+        // Check the move from the ghost's next cell, as the AI would
+        let ghost = &self.ghosts[idx];
+        let (row, col) = ghost.next_loc.get_neighbor_coords(dir);
+        if self.wall_at(row, col) || dir == ghost.loc.dir.reverse() {
+            return Some(MoveOutcome::Blocked);
+        }
+
+        // Replace the AI's plan right away, if there is one to replace
+        if !self.ghost_roaming(idx) {
+            return Some(MoveOutcome::Buffered);
+        }
+        self.ghosts[idx].next_loc.update_dir(dir);
+        Some(MoveOutcome::Moved)
+    }
+
+    // Direction asked for by a ghost's controller, if it is still in control
+    pub(crate) fn controlled_ghost_dir(&self, idx: usize) -> Option<Direction> {
+        if !self.ghost_controller_active(idx) {
+            return None;
+        }
+        self.ghosts[idx]
+            .controller
+            .map(|controller| controller.dir)
+            .filter(|&dir| dir != Direction::None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // First direction a ghost could be steered in from its next cell
+    fn open_dir(gs: &GameState, idx: usize) -> Option<Direction> {
+        let ghost = &gs.ghosts[idx];
+        [
+            Direction::Up,
+            Direction::Left,
+            Direction::Down,
+            Direction::Right,
+        ]
+        .into_iter()
+        .find(|&dir| {
+            let (row, col) = ghost.next_loc.get_neighbor_coords(dir);
+            !gs.wall_at(row, col) && dir != ghost.loc.dir.reverse()
+        })
+    }
+
+    #[test]
+    fn steering_a_roaming_ghost_moves_it() {
+        let mut gs = GameState::new();
+        gs.play();
+        while !gs.ghost_roaming(RED as usize) {
+            gs.step_ticks(1);
+        }
+        let dir = open_dir(&gs, RED as usize).unwrap();
+        assert_eq!(
+            gs.steer_ghost(RED as usize, 1, dir),
+            Some(MoveOutcome::Moved)
+        );
+        assert_eq!(gs.ghosts[RED as usize].next_loc.dir, dir);
+    }
+
+    #[test]
+    fn steering_a_ghost_in_the_house_is_buffered() {
+        let mut gs = GameState::new();
+        gs.play();
+        let idx = ORANGE as usize;
+        let dir = loop {
+            gs.step_ticks(1);
+            if let Some(dir) = open_dir(&gs, idx) {
+                break dir;
+            }
+        };
+        assert!(gs.ghosts[idx].is_spawning());
+        assert_eq!(gs.steer_ghost(idx, 1, dir), Some(MoveOutcome::Buffered));
+        assert_eq!(gs.controlled_ghost_dir(idx), Some(dir));
+        assert_eq!(gs.steer_ghost(idx, 2, dir), None);
+    }
+}
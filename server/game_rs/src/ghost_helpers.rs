@@ -20,9 +20,12 @@ impl GhostState {
         }
 
         self.set_spawning(true);
+        self.set_eyes(false);
+        self.set_released(self.color == RED);
         self.set_trapped_steps(GHOST_TRAPPED_STEPS[self.color as usize]);
         self.set_fright_steps(0);
         self.loc.copy_from(&empty_loc());
+        self.prev_loc.copy_from(&empty_loc());
         self.next_loc.copy_from(&spawn_locs[self.color as usize]);
     }
 
@@ -36,6 +39,8 @@ impl GhostState {
 
         self.set_spawning(true);
         self.set_eaten(true);
+        self.set_eyes(false);
+        self.set_released(true);
         self.loc.copy_from(&empty_loc());
 
         // Red goes to pink's spawn, others go to their own spawn
@@ -50,6 +55,53 @@ impl GhostState {
             .update_dir(crate::direction::Direction::Up);
     }
 
+    /// Send an eaten ghost back to the ghost house as eyes (harmless, visible)
+    pub fn become_eyes(&mut self) {
+        if self.color >= NUM_ACTIVE_GHOSTS {
+            return;
+        }
+
+        self.set_eyes(true);
+        self.set_fright_steps(0);
+    }
+
+    /// Advance the ghost to its planned location (before serialization)
+    pub fn update(&mut self) {
+        self.update_fright();
+        self.advance();
+    }
+
+    /// Decrement the ghost's frightened steps count if necessary
+    pub fn update_fright(&mut self) {
+        if self.is_frightened() {
+            self.dec_fright_steps();
+        }
+    }
+
+    /// Move the ghost into its planned location, without touching its timers
+    pub fn advance(&mut self) {
+        let spawn_locs = ghost_spawn_locs();
+
+        // If the ghost is at the red spawn point and not moving downwards,
+        // we can mark it as done spawning
+        if self.loc.collides_with(&spawn_locs[RED as usize])
+            && self.loc.dir != crate::direction::Direction::Down
+        {
+            self.set_spawning(false);
+        }
+
+        // Set the ghost to be no longer eaten, if applicable
+        if self.is_eaten() {
+            self.set_eaten(false);
+            self.set_fright_steps(0);
+        }
+
+        // Remember where the ghost came from (to catch swaps with Pacman),
+        // then copy the next location into the current location
+        self.prev_loc.copy_from(&self.loc);
+        self.loc.copy_from(&self.next_loc.clone());
+    }
+
     /// Get the ghost's current position
     pub fn get_pos(&self) -> (i8, i8) {
         self.loc.get_coords()
@@ -0,0 +1,202 @@
+// ghost_house.rs - Pellet-counter based ghost house release (arcade rules)
+
+use tracing::info;
+
+use crate::constants::*;
+use crate::events::GameEvent;
+use crate::rules::HouseRelease;
+use crate::state::GameState;
+
+// Order in which waiting ghosts are preferred for release
+const RELEASE_ORDER: [u8; 3] = [PINK, CYAN, ORANGE];
+
+impl GameState {
+    /*************************** Waiting Ghosts ***************************/
+
+    /// Whether a ghost is being held in the house by the dot counter rules
+    pub fn ghost_waiting(&self, idx: usize) -> bool {
+        let ghost = &self.ghosts[idx];
+        self.rules.house_release == HouseRelease::DotCounter
+            && ghost.is_spawning()
+            && !ghost.is_released()
+    }
+
+    // The first ghost (pink, cyan, then orange) still waiting in the house
+    fn preferred_waiting_ghost(&self) -> Option<usize> {
+        RELEASE_ORDER
+            .iter()
+            .map(|&color| color as usize)
+            .find(|&idx| self.ghost_waiting(idx))
+    }
+
+    // Let a waiting ghost leave the house
+    fn release_ghost(&mut self, idx: usize) {
+        info!(
+            "GAME: {} released from the ghost house (t = {})",
+            GHOST_NAMES[idx], self.curr_ticks
+        );
+        self.ghosts[idx].set_released(true);
+        self.emit(GameEvent::GhostReleased { ghost: idx as u8 });
+    }
+
+    /*************************** Dot Counters ***************************/
+
+    /// Personal pellet limit of a ghost at the current level
+    pub fn house_dot_limit(&self, idx: usize) -> u16 {
+        let row = (self.get_level().max(1) as usize - 1).min(HOUSE_DOT_LIMITS.len() - 1);
+        HOUSE_DOT_LIMITS[row][idx]
+    }
+
+    /// Count a collected pellet towards releasing the next waiting ghost
+    pub fn count_house_pellet(&mut self) {
+        if self.rules.house_release != HouseRelease::DotCounter {
+            return;
+        }
+
+        // Eating a pellet restarts the release timer
+        self.house_pellet_timer = 0;
+
+        // After a death, the global counter takes over from the personal ones
+        if self.global_dot_counter_active {
+            self.global_dot_counter += 1;
+            for &color in RELEASE_ORDER.iter() {
+                let idx = color as usize;
+                if self.global_dot_counter == HOUSE_GLOBAL_DOT_LIMITS[idx]
+                    && self.ghost_waiting(idx)
+                {
+                    self.release_ghost(idx);
+                }
+            }
+
+            // Once the counter reaches orange's limit, go back to personal counters
+            let orange = ORANGE as usize;
+            if self.global_dot_counter >= HOUSE_GLOBAL_DOT_LIMITS[orange] {
+                self.global_dot_counter_active = false;
+            }
+            return;
+        }
+
+        // Otherwise, only the preferred ghost counts pellets
+        if let Some(idx) = self.preferred_waiting_ghost() {
+            self.ghosts[idx].inc_dot_counter();
+        }
+        self.update_house_release();
+    }
+
+    /// Release the preferred ghost if its counter (or the pellet timer) allows
+    pub fn update_house_release(&mut self) {
+        if self.rules.house_release != HouseRelease::DotCounter {
+            return;
+        }
+
+        let Some(idx) = self.preferred_waiting_ghost() else {
+            return;
+        };
+
+        // Personal counter reached its limit
+        if !self.global_dot_counter_active
+            && self.ghosts[idx].get_dot_counter() >= self.house_dot_limit(idx)
+        {
+            self.release_ghost(idx);
+            return;
+        }
+
+        // Pacman hasn't eaten a pellet in a while
+        let timer_limit = if self.get_level() >= 5 {
+            HOUSE_PELLET_TIMER_STEPS_LATE
+        } else {
+            HOUSE_PELLET_TIMER_STEPS
+        };
+        if self.house_pellet_timer >= timer_limit {
+            self.house_pellet_timer = 0;
+            self.release_ghost(idx);
+        }
+    }
+
+    /// Advance the pellet timer by one step
+    pub fn inc_house_pellet_timer(&mut self) {
+        if self.rules.house_release != HouseRelease::DotCounter {
+            return;
+        }
+        self.house_pellet_timer = self.house_pellet_timer.saturating_add(1);
+    }
+
+    /// Reset the house counters (after a death, the global counter is used)
+    pub fn reset_house_counters(&mut self, after_death: bool) {
+        self.house_pellet_timer = 0;
+        self.global_dot_counter = 0;
+        self.global_dot_counter_active = after_death;
+
+        // Personal counters only start over on a new level
+        if !after_death {
+            for ghost in self.ghosts.iter_mut() {
+                ghost.set_dot_counter(0);
+            }
+        }
+
+        // With the dot counter rules, the counters decide when ghosts leave;
+        // otherwise, ghosts held in the house wait out their usual time
+        let dot_counter = self.rules.house_release == HouseRelease::DotCounter;
+        for ghost in self.ghosts.iter_mut().filter(|ghost| !ghost.is_released()) {
+            let steps = if dot_counter {
+                0
+            } else {
+                GHOST_TRAPPED_STEPS[ghost.color as usize]
+            };
+            ghost.set_trapped_steps(steps);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::GameRules;
+
+    fn dot_counter_rules() -> GameRules {
+        GameRules {
+            house_release: HouseRelease::DotCounter,
+            ..Default::default()
+        }
+    }
+
+    fn trapped_steps(gs: &GameState) -> Vec<u8> {
+        gs.ghosts.iter().map(|ghost| ghost.trapped_steps).collect()
+    }
+
+    #[test]
+    fn switching_to_dot_counters_matches_a_new_game() {
+        let built = GameState::with_rules(dot_counter_rules());
+        let mut switched = GameState::new();
+        switched.set_rules(dot_counter_rules());
+        assert_eq!(trapped_steps(&switched), trapped_steps(&built));
+        assert!(trapped_steps(&switched).iter().all(|&steps| steps == 0));
+        assert!(switched.ghost_waiting(PINK as usize));
+    }
+
+    #[test]
+    fn switching_back_restores_the_timed_release() {
+        let mut gs = GameState::with_rules(dot_counter_rules());
+        gs.set_rules(GameRules::default());
+        assert_eq!(trapped_steps(&gs), trapped_steps(&GameState::new()));
+        assert!(!gs.ghost_waiting(PINK as usize));
+    }
+
+    #[test]
+    fn other_rule_changes_keep_the_counters() {
+        // Pink leaves on the first pellet, and cyan starts counting
+        let mut gs = GameState::with_rules(dot_counter_rules());
+        for _ in 0..3 {
+            gs.count_house_pellet();
+        }
+        let cyan = CYAN as usize;
+        assert_eq!(gs.ghosts[cyan].get_dot_counter(), 2);
+
+        gs.set_rules(GameRules {
+            buffered_turns: true,
+            ..dot_counter_rules()
+        });
+        assert_eq!(gs.ghosts[cyan].get_dot_counter(), 2);
+        assert!(gs.ghosts[PINK as usize].is_released());
+    }
+}
@@ -3,11 +3,14 @@
 use serde::{Deserialize, Serialize};
 
 use crate::constants::*;
+use crate::ghost_control::GhostController;
 use crate::location::LocationState;
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct GhostState {
     pub loc: LocationState,
+    #[serde(skip, default = "empty_loc")]
+    pub prev_loc: LocationState,
     pub next_loc: LocationState,
     pub scatter_target: LocationState,
     pub color: u8,
@@ -15,6 +18,14 @@ pub struct GhostState {
     pub fright_steps: u8,
     pub spawning: bool,
     pub eaten: bool,
+    pub eyes: bool,
+    pub elroy: u8,
+    pub released: bool,
+    pub dot_counter: u16,
+    #[serde(skip)]
+    pub speed_credit: u16,
+    #[serde(skip)]
+    pub controller: Option<GhostController>,
 }
 
 impl GhostState {
@@ -32,6 +43,7 @@ impl GhostState {
 
         Self {
             loc: empty_loc(),
+            prev_loc: empty_loc(),
             next_loc,
             scatter_target: scatter_targets[color_idx].clone(),
             color,
@@ -39,6 +51,12 @@ impl GhostState {
             fright_steps: 0,
             spawning: true,
             eaten: false,
+            eyes: false,
+            elroy: 0,
+            released: color == RED,
+            dot_counter: 0,
+            speed_credit: 0,
+            controller: None,
         }
     }
 
@@ -97,4 +115,50 @@ impl GhostState {
     pub fn is_eaten(&self) -> bool {
         self.eaten
     }
+
+    /************************** Ghost Eyes State **************************/
+
+    pub fn set_eyes(&mut self, eyes: bool) {
+        self.eyes = eyes;
+    }
+
+    pub fn is_eyes(&self) -> bool {
+        self.eyes
+    }
+
+    /************************** Ghost Elroy Level **************************/
+
+    pub fn set_elroy(&mut self, elroy: u8) {
+        self.elroy = elroy;
+    }
+
+    pub fn get_elroy(&self) -> u8 {
+        self.elroy
+    }
+
+    pub fn is_elroy(&self) -> bool {
+        self.elroy > 0
+    }
+
+    /************************ Ghost House Release *************************/
+
+    pub fn set_released(&mut self, released: bool) {
+        self.released = released;
+    }
+
+    pub fn is_released(&self) -> bool {
+        self.released
+    }
+
+    pub fn set_dot_counter(&mut self, count: u16) {
+        self.dot_counter = count;
+    }
+
+    pub fn inc_dot_counter(&mut self) {
+        self.dot_counter = self.dot_counter.saturating_add(1);
+    }
+
+    pub fn get_dot_counter(&self) -> u16 {
+        self.dot_counter
+    }
 }
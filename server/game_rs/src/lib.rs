@@ -3,13 +3,30 @@ use pyo3::prelude::*;
 pub mod constants;
 pub mod direction;
 pub mod location;
+pub mod fruit;
 pub mod ghost_state;
 pub mod ghost_helpers;
+pub mod ghost_house;
+pub mod ghost_control;
+pub mod ghost_ai;
 pub mod state;
+pub mod events;
 pub mod game_modes;
 pub mod game_helpers;
+pub mod moves;
+pub mod tracking;
+pub mod collision;
+pub mod agents;
+pub mod rules;
+pub mod search;
+pub mod mcts;
+pub mod speed;
+pub mod serialize;
+pub mod clients;
 pub mod commands;
 pub mod logging;
+pub mod stats;
+pub mod referee;
 pub mod engine;
 mod pyo3_bindings;
 
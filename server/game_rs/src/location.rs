@@ -31,6 +31,17 @@ impl LocationState {
         self.row == other.row && self.col == other.col
     }
 
+    /// Check if this location and another swapped cells in their last moves
+    /// (passing through each other without ever sharing a cell)
+    pub fn swapped_with(
+        &self,
+        prev: &LocationState,
+        other: &LocationState,
+        other_prev: &LocationState,
+    ) -> bool {
+        self.collides_with(other_prev) && prev.collides_with(other)
+    }
+
     /// Check if this location is the empty/invalid location (32, 32)
     pub fn is_empty(&self) -> bool {
         self.row == 32 && self.col == 32
@@ -0,0 +1,442 @@
+// mcts.rs - Monte Carlo tree search over Pacman's moves
+//
+// An alternative to the exhaustive search in search.rs: each iteration replays
+// a sequence of Pacman moves from the root (picked by UCT), then plays on with
+// a cheap rollout policy, and scores where the game ended up. The tree is
+// open-loop: nodes hold move statistics rather than game states, and every
+// iteration reseeds the RNG, so random ghost moves are averaged over
+// naturally. With several threads, each grows its own tree and the statistics
+// of the first moves are merged at the end.
+
+use std::panic::resume_unwind;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::direction::Direction;
+use crate::search::{
+    evaluate, is_terminal, legal_actions, nearest_pellet_dist, silently, simulate_step,
+    simulation_root,
+};
+use crate::state::GameState;
+
+/// How rollouts choose Pacman's moves
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RolloutPolicy {
+    /// Any legal move, at random
+    Random,
+    /// Towards the nearest pellet, away from nearby ghosts
+    #[default]
+    Greedy,
+}
+
+/// Settings for a Monte Carlo tree search
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct MctsConfig {
+    /// Time budget in milliseconds (0 for none, if iterations are capped;
+    /// with neither capped, the default budget applies)
+    pub time_budget_ms: u64,
+    /// Most iterations per thread (0 for no cap)
+    pub iterations: u32,
+    /// Threads, each growing its own tree
+    pub threads: u8,
+    /// UCT exploration constant (for rewards in units of reward_scale)
+    pub exploration: f64,
+    /// Points per unit of reward
+    pub reward_scale: f64,
+    /// Deepest the tree grows, in Pacman moves
+    pub max_tree_depth: u8,
+    /// Pacman moves played by the rollout policy after leaving the tree
+    pub rollout_depth: u8,
+    pub rollout: RolloutPolicy,
+    /// Chance of a random move in a greedy rollout (0 - 1)
+    pub rollout_epsilon: f64,
+    /// Value of losing a life, in points
+    pub death_penalty: f64,
+    /// Seed for the iterations' RNGs (taken from the game RNG if not given)
+    pub seed: Option<u64>,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        Self {
+            time_budget_ms: 100,
+            iterations: 0,
+            threads: 1,
+            exploration: std::f64::consts::SQRT_2,
+            reward_scale: 100.0,
+            max_tree_depth: 20,
+            rollout_depth: 10,
+            rollout: RolloutPolicy::Greedy,
+            rollout_epsilon: 0.1,
+            death_penalty: 1000.0,
+            seed: None,
+        }
+    }
+}
+
+impl MctsConfig {
+    /// Read MCTS settings (budget, threads, rollout policy) from a JSON
+    /// object; fields it leaves out take their default values
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+/// Statistics of one of Pacman's moves from the root
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ActionStats {
+    pub action: Direction,
+    pub visits: u32,
+    /// Mean value of the iterations through this move, in points
+    pub mean_value: f64,
+}
+
+/// Outcome of a Monte Carlo tree search
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct MctsResult {
+    /// Most visited move (None to stay put)
+    pub action: Direction,
+    pub actions: Vec<ActionStats>,
+    /// Iterations over all threads
+    pub iterations: u64,
+    pub threads: u8,
+    pub elapsed_ms: u64,
+}
+
+// A node of the tree: the move leading to it, and its statistics
+struct Node {
+    action: Direction,
+    children: Vec<usize>,
+    expanded: bool,
+    visits: u32,
+    total_reward: f64,
+}
+
+impl Node {
+    fn new(action: Direction) -> Self {
+        Self {
+            action,
+            children: Vec::new(),
+            expanded: false,
+            visits: 0,
+            total_reward: 0.0,
+        }
+    }
+}
+
+// One thread's tree
+struct Tree<'a> {
+    config: &'a MctsConfig,
+    root: &'a GameState,
+    nodes: Vec<Node>,
+    rng: StdRng,
+}
+
+impl<'a> Tree<'a> {
+    fn new(config: &'a MctsConfig, root: &'a GameState, seed: u64) -> Self {
+        Self {
+            config,
+            root,
+            nodes: vec![Node::new(Direction::None)],
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    // Grow the tree until the time or iterations run out (at least once)
+    fn run(&mut self, deadline: Option<Instant>) -> u64 {
+        let mut iterations = 0;
+        loop {
+            self.iterate();
+            iterations += 1;
+            if self.config.iterations > 0 && iterations >= self.config.iterations as u64 {
+                break;
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                break;
+            }
+        }
+        iterations
+    }
+
+    // Select, expand, roll out and back up once
+    fn iterate(&mut self) {
+        let mut gs = self.root.clone();
+        gs.rng = StdRng::seed_from_u64(self.rng.gen());
+        let mut path = vec![0];
+        let mut node = 0;
+
+        // Follow the tree down, adding a node where it ends
+        while !is_terminal(&gs) && path.len() <= self.config.max_tree_depth as usize {
+            if !self.nodes[node].expanded {
+                self.expand(node, &gs);
+            }
+            let Some(child) = self.select(node) else {
+                break;
+            };
+            simulate_step(&mut gs, self.nodes[child].action);
+            path.push(child);
+            node = child;
+            if self.nodes[child].visits == 0 {
+                break;
+            }
+        }
+
+        // Play on with the rollout policy
+        for _ in 0..self.config.rollout_depth {
+            if is_terminal(&gs) {
+                break;
+            }
+            let action = self.rollout_action(&gs);
+            simulate_step(&mut gs, action);
+        }
+
+        let value = evaluate(
+            &gs,
+            self.root.curr_score,
+            self.root.curr_lives,
+            self.config.death_penalty,
+        );
+        let reward = value.total() / self.config.reward_scale;
+        for &idx in path.iter() {
+            self.nodes[idx].visits += 1;
+            self.nodes[idx].total_reward += reward;
+        }
+    }
+
+    // Add a child for each of Pacman's legal moves
+    fn expand(&mut self, node: usize, gs: &GameState) {
+        for action in legal_actions(gs) {
+            self.nodes.push(Node::new(action));
+            let child = self.nodes.len() - 1;
+            self.nodes[node].children.push(child);
+        }
+        self.nodes[node].expanded = true;
+    }
+
+    // Child to visit next: an unvisited one, or else the best by UCT
+    fn select(&self, node: usize) -> Option<usize> {
+        let children = &self.nodes[node].children;
+        if let Some(&child) = children
+            .iter()
+            .find(|&&child| self.nodes[child].visits == 0)
+        {
+            return Some(child);
+        }
+        let log_visits = (self.nodes[node].visits.max(1) as f64).ln();
+        children
+            .iter()
+            .copied()
+            .max_by(|&a, &b| self.uct(a, log_visits).total_cmp(&self.uct(b, log_visits)))
+    }
+
+    fn uct(&self, child: usize, log_visits: f64) -> f64 {
+        let node = &self.nodes[child];
+        let visits = node.visits as f64;
+        node.total_reward / visits + self.config.exploration * (log_visits / visits).sqrt()
+    }
+
+    // Pacman's move in a rollout
+    fn rollout_action(&mut self, gs: &GameState) -> Direction {
+        let actions = legal_actions(gs);
+        let greedy = self.config.rollout == RolloutPolicy::Greedy
+            && self.rng.gen::<f64>() >= self.config.rollout_epsilon;
+        if !greedy {
+            return *actions.choose(&mut self.rng).unwrap_or(&Direction::None);
+        }
+
+        // Closest to a pellet, among the moves not next to a ghost
+        let (row, col) = gs.pacman_loc.get_coords();
+        actions
+            .iter()
+            .copied()
+            .filter(|&action| action != Direction::None)
+            .map(|action| {
+                let (next_row, next_col) = gs.pacman_loc.get_neighbor_coords(action);
+                let danger = ghost_adjacent(gs, next_row, next_col);
+                let dist = nearest_pellet_dist(gs, next_row, next_col).unwrap_or(u16::MAX);
+                (action, (danger, dist))
+            })
+            .min_by_key(|&(_, key)| key)
+            .filter(|&(_, (danger, _))| !danger || ghost_adjacent(gs, row, col))
+            .map(|(action, _)| action)
+            .unwrap_or(Direction::None)
+    }
+
+    // Statistics of the root's moves
+    fn root_stats(&self) -> Vec<ActionStats> {
+        self.nodes[0]
+            .children
+            .iter()
+            .map(|&child| {
+                let node = &self.nodes[child];
+                ActionStats {
+                    action: node.action,
+                    visits: node.visits,
+                    mean_value: if node.visits > 0 {
+                        node.total_reward / node.visits as f64 * self.config.reward_scale
+                    } else {
+                        0.0
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+// Whether a (non-frightened) ghost is on or next to a cell
+fn ghost_adjacent(gs: &GameState, row: i8, col: i8) -> bool {
+    gs.ghosts.iter().any(|ghost| {
+        if ghost.loc.is_empty() || ghost.is_frightened() || ghost.is_eyes() {
+            return false;
+        }
+        let (ghost_row, ghost_col) = ghost.loc.get_coords();
+        (ghost_row as i32 - row as i32).abs() + (ghost_col as i32 - col as i32).abs() <= 1
+    })
+}
+
+// Merge the root statistics of several trees (which list the same moves)
+fn merge_stats(trees: Vec<Vec<ActionStats>>) -> Vec<ActionStats> {
+    let mut merged: Vec<ActionStats> = Vec::new();
+    for stats in trees {
+        for stat in stats {
+            match merged
+                .iter_mut()
+                .find(|merged| merged.action == stat.action)
+            {
+                Some(merged) => {
+                    let visits = merged.visits + stat.visits;
+                    if visits > 0 {
+                        merged.mean_value = (merged.mean_value * merged.visits as f64
+                            + stat.mean_value * stat.visits as f64)
+                            / visits as f64;
+                    }
+                    merged.visits = visits;
+                }
+                None => merged.push(stat),
+            }
+        }
+    }
+    merged
+}
+
+impl GameState {
+    /// Monte Carlo tree search of Pacman's moves from this state (the state
+    /// itself is unchanged)
+    pub fn mcts(&self, config: MctsConfig) -> MctsResult {
+        let start = Instant::now();
+        let root = simulation_root(self);
+
+        // A search needs an end: with no iteration cap, there is a time budget
+        let budget_ms = if config.time_budget_ms == 0 && config.iterations == 0 {
+            MctsConfig::default().time_budget_ms
+        } else {
+            config.time_budget_ms
+        };
+        let budget = Duration::from_millis(budget_ms);
+        let deadline = (!budget.is_zero()).then(|| start + budget);
+        let threads = config.threads.max(1);
+        let config = &config;
+
+        // Without a seed, searches are repeatable from the game RNG
+        let seed = config.seed.unwrap_or_else(|| root.rng.clone().gen());
+        let mut seeds = StdRng::seed_from_u64(seed);
+        let seeds: Vec<u64> = (0..threads).map(|_| seeds.gen()).collect();
+
+        let outcomes: Vec<(Vec<ActionStats>, u64)> = thread::scope(|scope| {
+            let handles: Vec<_> = seeds
+                .into_iter()
+                .map(|seed| {
+                    let root = &root;
+                    scope.spawn(move || {
+                        silently(|| {
+                            let mut tree = Tree::new(config, root, seed);
+                            let iterations = tree.run(deadline);
+                            (tree.root_stats(), iterations)
+                        })
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap_or_else(|panic| resume_unwind(panic)))
+                .collect()
+        });
+
+        let iterations = outcomes.iter().map(|(_, iterations)| iterations).sum();
+        let actions = merge_stats(outcomes.into_iter().map(|(stats, _)| stats).collect());
+
+        // Ties go to the first move listed
+        let action = actions
+            .iter()
+            .fold(None::<&ActionStats>, |best, stat| match best {
+                Some(best) if best.visits >= stat.visits => Some(best),
+                _ => Some(stat),
+            })
+            .map(|stat| stat.action)
+            .unwrap_or(Direction::None);
+        MctsResult {
+            action,
+            actions,
+            iterations,
+            threads,
+            elapsed_ms: start.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(iterations: u32, threads: u8) -> MctsConfig {
+        MctsConfig {
+            time_budget_ms: 0,
+            iterations,
+            threads,
+            seed: Some(11),
+            ..Default::default()
+        }
+    }
+
+    fn game() -> GameState {
+        let mut gs = GameState::new();
+        gs.play();
+        gs.step_ticks(40);
+        gs
+    }
+
+    #[test]
+    fn iterations_are_capped_per_thread() {
+        let result = game().mcts(config(50, 3));
+        assert_eq!(result.threads, 3);
+        assert_eq!(result.iterations, 150);
+        let visits: u64 = result.actions.iter().map(|stat| stat.visits as u64).sum();
+        assert_eq!(visits, 150);
+    }
+
+    #[test]
+    fn action_is_the_most_visited() {
+        let result = game().mcts(config(200, 2));
+        let most = result.actions.iter().map(|stat| stat.visits).max().unwrap();
+        let best = result
+            .actions
+            .iter()
+            .find(|stat| stat.visits == most)
+            .unwrap();
+        assert_eq!(result.action, best.action);
+    }
+
+    #[test]
+    fn search_without_limits_uses_the_default_budget() {
+        let result = game().mcts(config(0, 1));
+        assert!(result.iterations > 0);
+        assert!(result.elapsed_ms >= MctsConfig::default().time_budget_ms);
+    }
+}
@@ -0,0 +1,317 @@
+// moves.rs - Buffered turns, tick-scheduled moves and continuous motion
+//
+// Bots driving a physical robot act with some latency, so they may want to
+// commit to a turn before reaching the intersection, or schedule moves ahead.
+// Simulated bots may instead let the engine keep Pacman moving on its own.
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::*;
+use crate::direction::Direction;
+use crate::game_helpers::MoveOutcome;
+use crate::state::GameState;
+
+/// A move to apply once the game reaches the given tick
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct QueuedMove {
+    pub tick: u64,
+    pub dir: Direction,
+}
+
+impl GameState {
+    /**************************** Buffered Turns ****************************/
+
+    /// The turn waiting for the next cell where it is legal (or None)
+    pub fn get_buffered_turn(&self) -> Direction {
+        self.buffered_turn
+    }
+
+    /*
+    With buffered turns enabled, a stored turn replaces a move straight ahead
+    as soon as Pacman can take it; a move in any other direction is a newer
+    turn, and replaces the stored one instead
+    */
+    pub(crate) fn resolve_buffered_turn(&mut self, dir: Direction) -> Direction {
+        if !self.rules.buffered_turns || self.buffered_turn == Direction::None {
+            return dir;
+        }
+        if dir != self.pacman_loc.dir {
+            self.clear_buffered_turn();
+            return dir;
+        }
+        let (row, col) = self.pacman_loc.get_neighbor_coords(self.buffered_turn);
+        if self.wall_at(row, col) {
+            return dir;
+        }
+        let turn = self.buffered_turn;
+        self.clear_buffered_turn();
+        turn
+    }
+
+    // Store a blocked move as a buffered turn, if enabled (a move straight
+    // ahead into a wall is only blocked, keeping any turn already buffered)
+    pub(crate) fn buffer_turn(&mut self, dir: Direction, heading: Direction) -> MoveOutcome {
+        if !self.rules.buffered_turns || dir == heading {
+            return MoveOutcome::Blocked;
+        }
+        self.buffered_turn = dir;
+        self.buffered_turn_cells = 0;
+        MoveOutcome::Buffered
+    }
+
+    // Count a cell moved without taking the buffered turn, dropping the turn
+    // once Pacman is too far past where it was asked for
+    pub(crate) fn age_buffered_turn(&mut self) {
+        if self.buffered_turn == Direction::None {
+            return;
+        }
+        self.buffered_turn_cells += 1;
+        if self.buffered_turn_cells > BUFFERED_TURN_CELLS {
+            self.clear_buffered_turn();
+        }
+    }
+
+    fn clear_buffered_turn(&mut self) {
+        self.buffered_turn = Direction::None;
+        self.buffered_turn_cells = 0;
+    }
+
+    /***************************** Move Queue *****************************/
+
+    /// Schedule a move for a given tick (moves for the same tick keep their
+    /// order); returns false if the queue is full
+    pub fn queue_move(&mut self, tick: u64, dir: Direction) -> bool {
+        if self.move_queue.len() >= MAX_QUEUED_MOVES {
+            return false;
+        }
+        let idx = self.move_queue.partition_point(|queued| queued.tick <= tick);
+        self.move_queue.insert(idx, QueuedMove { tick, dir });
+        true
+    }
+
+    /// Number of moves waiting in the queue
+    pub fn num_queued_moves(&self) -> usize {
+        self.move_queue.len()
+    }
+
+    /// Apply every queued move that is due (kept while the game is paused)
+    pub fn apply_queued_moves(&mut self) {
+        while !self.is_paused() && !self.get_pause_on_update() {
+            match self.move_queue.front() {
+                Some(queued) if queued.tick <= self.curr_ticks => {
+                    let dir = queued.dir;
+                    self.move_queue.pop_front();
+                    self.move_pacman_dir(dir);
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Forget any buffered turn and queued moves (e.g. when Pacman respawns)
+    pub fn clear_moves(&mut self) {
+        self.clear_buffered_turn();
+        self.move_queue.clear();
+    }
+
+    /*************************** Continuous Motion ***************************/
+
+    /// Point Pacman in a new direction, if the cell that way is open
+    /// (otherwise the turn may be buffered)
+    pub fn turn_pacman(&mut self, dir: Direction) -> MoveOutcome {
+        let (row, col) = self.pacman_loc.get_neighbor_coords(dir);
+        if self.wall_at(row, col) {
+            return self.buffer_turn(dir, self.pacman_loc.dir);
+        }
+
+        // A turn that can be taken right away replaces any buffered one
+        if dir != self.pacman_loc.dir {
+            self.clear_buffered_turn();
+        }
+        self.pacman_loc.update_dir(dir);
+        MoveOutcome::Moved
+    }
+
+    /// Keep Pacman moving in its current direction, stopping at walls
+    /// (continuous motion only; paced by the speed model if enabled)
+    pub fn advance_pacman(&mut self) {
+        if !self.rules.continuous_motion
+            || self.is_paused()
+            || self.get_pause_on_update()
+            || self.pacman_loc.is_empty()
+        {
+            return;
+        }
+
+        let ready = if self.rules.speed_model.is_some() {
+            self.pacman_step_ready()
+        } else {
+            self.update_ready()
+        };
+        if !ready {
+            return;
+        }
+
+        let dir = self.resolve_buffered_turn(self.pacman_loc.dir);
+        if dir != Direction::None {
+            self.step_pacman(dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::GameRules;
+
+    // A game with buffered turns, with Pacman at the spawn (23, 13) heading right
+    fn buffered_game() -> GameState {
+        let mut gs = GameState::with_rules(GameRules {
+            buffered_turns: true,
+            ..Default::default()
+        });
+        gs.play();
+        assert_eq!(gs.pacman_loc.get_coords(), (23, 13));
+        assert_eq!(gs.pacman_loc.dir, Direction::Right);
+        gs
+    }
+
+    #[test]
+    fn buffered_turn_taken_when_legal() {
+        let mut gs = buffered_game();
+        assert_eq!(gs.move_pacman_dir(Direction::Up), MoveOutcome::Buffered);
+        assert_eq!(gs.pacman_loc.dir, Direction::Right);
+
+        // The corridor up opens at column 15
+        gs.move_pacman_dir(Direction::Right);
+        gs.move_pacman_dir(Direction::Right);
+        assert_eq!(gs.pacman_loc.get_coords(), (23, 15));
+        gs.move_pacman_dir(Direction::Right);
+        assert_eq!(gs.pacman_loc.get_coords(), (22, 15));
+        assert_eq!(gs.get_buffered_turn(), Direction::None);
+    }
+
+    #[test]
+    fn buffered_turn_expires() {
+        let mut gs = buffered_game();
+        assert_eq!(gs.move_pacman_dir(Direction::Down), MoveOutcome::Buffered);
+
+        // The corridor down only opens at column 18, too far along
+        for _ in 0..=BUFFERED_TURN_CELLS {
+            gs.move_pacman_dir(Direction::Right);
+        }
+        assert_eq!(gs.get_buffered_turn(), Direction::None);
+        while gs.pacman_loc.col < 19 {
+            gs.move_pacman_dir(Direction::Right);
+        }
+        assert_eq!(gs.pacman_loc.get_coords(), (23, 19));
+    }
+
+    #[test]
+    fn newer_turn_replaces_buffered_turn() {
+        let mut gs = buffered_game();
+        assert_eq!(gs.move_pacman_dir(Direction::Up), MoveOutcome::Buffered);
+        assert_eq!(gs.move_pacman_dir(Direction::Left), MoveOutcome::Moved);
+        assert_eq!(gs.pacman_loc.get_coords(), (23, 12));
+        assert_eq!(gs.get_buffered_turn(), Direction::None);
+    }
+
+    #[test]
+    fn move_into_wall_ahead_keeps_buffered_turn() {
+        // Facing the wall at (23, 4), with walls above
+        let mut gs = buffered_game();
+        gs.pacman_loc.update_coords(23, 3);
+        assert_eq!(gs.move_pacman_dir(Direction::Up), MoveOutcome::Buffered);
+        assert_eq!(gs.move_pacman_dir(Direction::Right), MoveOutcome::Blocked);
+        assert_eq!(gs.pacman_loc.get_coords(), (23, 3));
+        assert_eq!(gs.get_buffered_turn(), Direction::Up);
+    }
+
+    #[test]
+    fn turn_into_wall_ahead_keeps_buffered_turn() {
+        let mut gs = buffered_game();
+        gs.rules.continuous_motion = true;
+        gs.pacman_loc.update_coords(23, 3);
+        assert_eq!(gs.turn_pacman(Direction::Up), MoveOutcome::Buffered);
+        assert_eq!(gs.turn_pacman(Direction::Right), MoveOutcome::Blocked);
+        assert_eq!(gs.get_buffered_turn(), Direction::Up);
+    }
+
+    // A game with continuous motion, with Pacman at the spawn heading right
+    fn continuous_game(buffered_turns: bool) -> GameState {
+        let mut gs = GameState::with_rules(GameRules {
+            continuous_motion: true,
+            buffered_turns,
+            ..Default::default()
+        });
+        gs.play();
+        gs
+    }
+
+    // Advance Pacman on the next update
+    fn advance_one_update(gs: &mut GameState) {
+        let period = gs.get_update_period() as u64;
+        gs.curr_ticks = (gs.curr_ticks / period + 1) * period;
+        gs.advance_pacman();
+    }
+
+    #[test]
+    fn pacman_keeps_moving_until_a_wall() {
+        let mut gs = continuous_game(false);
+
+        // Only on updates
+        gs.curr_ticks = 1;
+        gs.advance_pacman();
+        assert_eq!(gs.pacman_loc.get_coords(), (23, 13));
+        advance_one_update(&mut gs);
+        assert_eq!(gs.pacman_loc.get_coords(), (23, 14));
+
+        // Pacman stops short of the wall at (23, 22)
+        for _ in 0..10 {
+            advance_one_update(&mut gs);
+        }
+        assert_eq!(gs.pacman_loc.get_coords(), (23, 21));
+        assert_eq!(gs.pacman_loc.dir, Direction::Right);
+    }
+
+    #[test]
+    fn pacman_stays_put_without_continuous_motion_or_when_paused() {
+        let mut gs = buffered_game();
+        advance_one_update(&mut gs);
+        assert_eq!(gs.pacman_loc.get_coords(), (23, 13));
+
+        let mut gs = continuous_game(false);
+        gs.pause();
+        advance_one_update(&mut gs);
+        assert_eq!(gs.pacman_loc.get_coords(), (23, 13));
+    }
+
+    #[test]
+    fn turns_only_change_the_heading() {
+        let mut gs = continuous_game(false);
+        assert_eq!(gs.turn_pacman(Direction::Left), MoveOutcome::Moved);
+        assert_eq!(gs.pacman_loc.get_coords(), (23, 13));
+        assert_eq!(gs.pacman_loc.dir, Direction::Left);
+        advance_one_update(&mut gs);
+        assert_eq!(gs.pacman_loc.get_coords(), (23, 12));
+
+        // Without buffered turns, a turn into a wall is dropped
+        assert_eq!(gs.move_pacman_dir(Direction::Down), MoveOutcome::Blocked);
+        assert_eq!(gs.pacman_loc.dir, Direction::Left);
+    }
+
+    #[test]
+    fn buffered_turn_taken_while_moving() {
+        let mut gs = continuous_game(true);
+        assert_eq!(gs.move_pacman_dir(Direction::Up), MoveOutcome::Buffered);
+
+        // The corridor up opens at column 15
+        advance_one_update(&mut gs);
+        advance_one_update(&mut gs);
+        assert_eq!(gs.pacman_loc.get_coords(), (23, 15));
+        advance_one_update(&mut gs);
+        assert_eq!(gs.pacman_loc.get_coords(), (22, 15));
+        assert_eq!(gs.pacman_loc.dir, Direction::Up);
+        assert_eq!(gs.get_buffered_turn(), Direction::None);
+    }
+}
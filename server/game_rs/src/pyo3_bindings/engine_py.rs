@@ -1,13 +1,53 @@
 // pyo3 bindings for engine.rs
 
+use std::collections::VecDeque;
+
 use pyo3::prelude::*;
-use crate::commands::interpret_command;
+use pyo3::types::PyBytes;
+use crate::clients::{ClientInfo, ClientRole};
+use crate::commands::{
+    execute_client_command, execute_command, interpret_command, CommandEffect, CommandError,
+    CommandResult, ResponseStatus,
+};
 use crate::engine::GameEngine;
-use crate::state::GameState;
+use crate::events::{EventRecord, MAX_QUEUED_EVENTS};
+use crate::ghost_ai::Difficulty;
+use crate::mcts::MctsConfig;
+use crate::rules::GameRules;
+use crate::search::SearchConfig;
 
 #[pyclass]
 pub struct PyGameEngine {
     engine: GameEngine,
+    events: VecDeque<EventRecord>,
+}
+
+impl PyGameEngine {
+    // Feed queued events to the match statistics, keeping them for Python
+    // (dropping the oldest if Python doesn't drain them)
+    fn collect_events(&mut self) {
+        for record in self.engine.state.drain_events() {
+            self.engine.stats.record(&record);
+            if self.events.len() >= MAX_QUEUED_EVENTS {
+                self.events.pop_front();
+            }
+            self.events.push_back(record);
+        }
+    }
+
+    // Carry out what an applied command asks of the engine, returning its
+    // status and the state JSON if the command requested it
+    fn handle_result(&mut self, result: CommandResult) -> PyResult<(&'static str, Option<String>)> {
+        let effect = self.engine.apply_effect(result).map_err(|e| match e {
+            CommandError::Forbidden { .. } => {
+                pyo3::exceptions::PyPermissionError::new_err(e.to_string())
+            }
+            _ => pyo3::exceptions::PyValueError::new_err(e.to_string()),
+        })?;
+        self.collect_events();
+        let state = (effect == CommandEffect::SendState).then(|| self.engine.state.serialize());
+        Ok((ResponseStatus::of(&effect).name(), state))
+    }
 }
 
 #[pymethods]
@@ -16,39 +56,90 @@ impl PyGameEngine {
     pub fn new(clock_rate: Option<u32>) -> Self {
         Self {
             engine: GameEngine::new(clock_rate.unwrap_or(24)),
+            events: VecDeque::new(),
         }
     }
 
     /// Run one tick of the game loop (for Python-driven game loops)
     pub fn step(&mut self) {
-        if self.engine.state.update_ready() {
-            self.engine.state.update_all_ghosts();
-            self.engine.state.try_respawn_pacman();
-
-            if self.engine.state.get_pause_on_update() {
-                self.engine.state.pause();
-                self.engine.state.set_pause_on_update(false);
-            }
-
-            self.engine.state.check_collisions();
-            self.engine.state.handle_step_events();
-            self.engine.state.plan_all_ghosts();
-        }
-
+        self.engine.state.update();
+        self.engine.history.record(&self.engine.state);
         if !self.engine.state.is_paused() {
             self.engine.state.next_tick();
         }
+
+        self.engine.stats.record_frame(
+            self.engine.state.get_curr_ticks(),
+            self.engine.state.is_paused(),
+        );
+        self.collect_events();
     }
 
     pub fn get_state_json(&self) -> String {
         self.engine.state.serialize()
     }
 
+    /// Get the state in the Go server's binary format
+    pub fn get_state_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.engine.state.serialize_bytes())
+    }
+
+    /// Take all queued game events as a JSON array
+    pub fn drain_events_json(&mut self) -> String {
+        self.collect_events();
+        serde_json::to_string(&std::mem::take(&mut self.events)).unwrap_or_default()
+    }
+
+    /// Get the statistics report of the current match as JSON
+    pub fn get_stats_json(&mut self) -> String {
+        self.collect_events();
+        self.engine.stats.report_json()
+    }
+
+    /// Whether the current match has ended (Pacman is out of lives)
+    pub fn is_match_over(&mut self) -> bool {
+        self.collect_events();
+        self.engine.stats.is_ended()
+    }
+
     pub fn send_command(&mut self, msg: Vec<u8>) -> bool {
         interpret_command(&msg, &mut self.engine.state, &self.engine.logger)
     }
 
-    pub fn get_score(&self) -> u16 {
+    /// Apply a legacy or framed command, raising ValueError if it is invalid;
+    /// returns its status ("applied", "ignored_paused", "blocked_by_wall",
+    /// "buffered" or "unconfirmed") and the state JSON if it requested it
+    pub fn execute_command(&mut self, msg: Vec<u8>) -> PyResult<(&'static str, Option<String>)> {
+        let result = execute_command(&msg, &mut self.engine.state, &self.engine.logger);
+        self.handle_result(result)
+    }
+
+    /// Apply a command on behalf of a client with the given role ("viewer",
+    /// "bot", "cv", "ghost" or "referee"), controlling the given Pacman, raising
+    /// PermissionError if it is not allowed (returns the same as execute_command)
+    #[pyo3(signature = (msg, client_id, role, pacman = 0))]
+    pub fn execute_client_command(
+        &mut self,
+        msg: Vec<u8>,
+        client_id: u32,
+        role: &str,
+        pacman: u8,
+    ) -> PyResult<(&'static str, Option<String>)> {
+        let role: ClientRole = role
+            .parse()
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        let client = ClientInfo::new(client_id, role).with_pacman(pacman);
+        let result =
+            execute_client_command(&msg, &client, &mut self.engine.state, &self.engine.logger);
+        self.handle_result(result)
+    }
+
+    /// Get the referee corrections applied so far as a JSON array
+    pub fn get_referee_log_json(&self) -> String {
+        serde_json::to_string(&self.engine.state.referee_log).unwrap_or_default()
+    }
+
+    pub fn get_score(&self) -> u32 {
         self.engine.state.get_score()
     }
 
@@ -56,6 +147,18 @@ impl PyGameEngine {
         self.engine.state.get_lives()
     }
 
+    pub fn get_num_pacmen(&self) -> usize {
+        self.engine.state.num_pacmen()
+    }
+
+    /// Get the position, score and lives of every Pacman as a JSON array
+    pub fn get_pacmen_json(&self) -> String {
+        let pacmen: Vec<_> = (0..self.engine.state.num_pacmen())
+            .filter_map(|pacman| self.engine.state.get_pacman_summary(pacman))
+            .collect();
+        serde_json::to_string(&pacmen).unwrap_or_default()
+    }
+
     pub fn get_level(&self) -> u8 {
         self.engine.state.get_level()
     }
@@ -64,8 +167,64 @@ impl PyGameEngine {
         self.engine.state.is_paused()
     }
 
+    /// Start a new match, as the engine loop does (keeping the rules)
     pub fn reset(&mut self) {
-        self.engine.state = GameState::new();
+        self.engine.reset();
+        self.collect_events();
+    }
+
+    /// Switch the engine to the rules in a GameRules JSON object; the match
+    /// in progress carries on under them (a new house release rule restarts
+    /// the ghosts' house counters)
+    pub fn set_rules_json(&mut self, json: &str) -> PyResult<()> {
+        let rules = GameRules::from_json(json)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
+        self.engine.state.set_rules(rules);
+        Ok(())
+    }
+
+    /// Use a ghost AI difficulty preset ("easy", "normal", "arcade", "hard"
+    /// or "expert")
+    pub fn set_difficulty(&mut self, difficulty: &str) -> PyResult<()> {
+        let difficulty: Difficulty = difficulty
+            .parse()
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        self.engine.state.rules.set_difficulty(difficulty);
+        Ok(())
+    }
+
+    pub fn get_rules_json(&self) -> String {
+        serde_json::to_string(&self.engine.state.rules).unwrap_or_default()
+    }
+
+    /// Expectimax search from the engine's current state, even while paused
+    /// (a SearchConfig JSON object, or None for the defaults); returns the
+    /// SearchResult as JSON. The GIL is released while searching, so other
+    /// Python threads run, but this engine stays borrowed until it returns
+    #[pyo3(signature = (config_json = None))]
+    pub fn search_json(&self, py: Python<'_>, config_json: Option<&str>) -> PyResult<String> {
+        let config = match config_json {
+            Some(json) => SearchConfig::from_json(json)
+                .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?,
+            None => SearchConfig::default(),
+        };
+        let result = py.detach(|| self.engine.state.search(config));
+        Ok(serde_json::to_string(&result).unwrap_or_default())
+    }
+
+    /// Monte Carlo tree search from the engine's current state (an MctsConfig
+    /// JSON object, or None for a 100 ms single-threaded search); returns the
+    /// MctsResult as JSON, with the visits and mean value of each root move.
+    /// The GIL is released while the search threads run
+    #[pyo3(signature = (config_json = None))]
+    pub fn mcts_json(&self, py: Python<'_>, config_json: Option<&str>) -> PyResult<String> {
+        let config = match config_json {
+            Some(json) => MctsConfig::from_json(json)
+                .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?,
+            None => MctsConfig::default(),
+        };
+        let result = py.detach(|| self.engine.state.mcts(config));
+        Ok(serde_json::to_string(&result).unwrap_or_default())
     }
 
     pub fn __repr__(&self) -> String {
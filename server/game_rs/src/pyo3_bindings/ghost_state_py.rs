@@ -73,10 +73,34 @@ impl PyGhostState {
         self.inner.is_eaten()
     }
 
+    pub fn set_eyes(&mut self, eyes: bool) {
+        self.inner.set_eyes(eyes);
+    }
+
+    pub fn is_eyes(&self) -> bool {
+        self.inner.is_eyes()
+    }
+
+    pub fn set_elroy(&mut self, elroy: u8) {
+        self.inner.set_elroy(elroy);
+    }
+
+    pub fn get_elroy(&self) -> u8 {
+        self.inner.get_elroy()
+    }
+
+    pub fn is_released(&self) -> bool {
+        self.inner.is_released()
+    }
+
+    pub fn get_dot_counter(&self) -> u16 {
+        self.inner.get_dot_counter()
+    }
+
     pub fn __repr__(&self) -> String {
         let (row, col) = self.inner.loc.get_coords();
         format!(
-            "PyGhostState(color={}, pos=({}, {}), fright={}, trapped={}, spawning={}, eaten={})",
+            "PyGhostState(color={}, pos=({}, {}), fright={}, trapped={}, spawning={}, eaten={}, eyes={}, elroy={})",
             self.inner.color,
             row,
             col,
@@ -84,6 +108,8 @@ impl PyGhostState {
             self.inner.trapped_steps,
             self.inner.spawning,
             self.inner.eaten,
+            self.inner.eyes,
+            self.inner.elroy,
         )
     }
 }
@@ -1,11 +1,30 @@
 // pyo3 bindings for state.rs
 
 use pyo3::prelude::*;
-use crate::commands::interpret_command;
+use pyo3::types::PyBytes;
+use crate::clients::LOCAL_CLIENT;
+use crate::commands::{execute_command, interpret_command, CommandEffect, ResponseStatus};
+use crate::constants::NUM_COLORS;
 use crate::direction::Direction;
+use crate::game_helpers::MoveOutcome;
+use crate::ghost_ai::Difficulty;
 use crate::logging::Logging;
+use crate::mcts::MctsConfig;
+use crate::rules::GameRules;
+use crate::search::SearchConfig;
 use crate::state::GameState;
 
+// Parse a direction name (or its wasd key)
+fn parse_dir(dir: &str) -> Option<Direction> {
+    match dir {
+        "up" | "w" => Some(Direction::Up),
+        "down" | "s" => Some(Direction::Down),
+        "left" | "a" => Some(Direction::Left),
+        "right" | "d" => Some(Direction::Right),
+        _ => None,
+    }
+}
+
 #[pyclass]
 pub struct PyGameState {
     pub inner: GameState,
@@ -23,20 +42,75 @@ impl PyGameState {
     }
 
     pub fn make_move(&mut self, dir: &str) {
-        let direction = match dir {
-            "up" | "w" => Direction::Up,
-            "down" | "s" => Direction::Down,
-            "left" | "a" => Direction::Left,
-            "right" | "d" => Direction::Right,
-            _ => return,
+        let Some(direction) = parse_dir(dir) else {
+            return;
         };
         self.inner.move_pacman_dir(direction);
     }
 
+    /// Schedule a move for a given tick; returns false if the queue is full
+    pub fn queue_move(&mut self, tick: u64, dir: &str) -> bool {
+        let Some(direction) = parse_dir(dir) else {
+            return false;
+        };
+        self.inner.queue_move(tick, direction)
+    }
+
+    pub fn clear_moves(&mut self) {
+        self.inner.clear_moves();
+    }
+
+    pub fn get_num_queued_moves(&self) -> usize {
+        self.inner.num_queued_moves()
+    }
+
+    /// How much the recent CV updates can be trusted (0 - 100)
+    pub fn get_tracking_confidence(&self) -> u8 {
+        self.inner.get_tracking_confidence()
+    }
+
+    pub fn get_buffered_turn(&self) -> String {
+        self.inner.get_buffered_turn().to_string()
+    }
+
+    /// Take control of a ghost; returns false if another client controls it
+    pub fn take_ghost_control(&mut self, color: u8) -> bool {
+        (color as usize) < NUM_COLORS && self.inner.take_ghost_control(color as usize, LOCAL_CLIENT)
+    }
+
+    /// Give a ghost back to the AI
+    pub fn release_ghost_control(&mut self, color: u8) -> bool {
+        (color as usize) < NUM_COLORS
+            && self
+                .inner
+                .release_ghost_control(color as usize, LOCAL_CLIENT)
+    }
+
+    /// Steer a ghost (a ghost not yet roaming takes the move later); returns
+    /// false if the move is illegal (or another client controls the ghost)
+    pub fn steer_ghost(&mut self, color: u8, dir: &str) -> bool {
+        let Some(direction) = parse_dir(dir) else {
+            return false;
+        };
+        if color as usize >= NUM_COLORS {
+            return false;
+        }
+        matches!(
+            self.inner
+                .steer_ghost(color as usize, LOCAL_CLIENT, direction),
+            Some(MoveOutcome::Moved | MoveOutcome::Buffered)
+        )
+    }
+
     pub fn make_move_absolute(&mut self, row: i8, col: i8) {
         self.inner.move_pacman_absolute(row, col);
     }
 
+    /// Move from a sub-cell pose, facing the heading (degrees, 0 = right, 90 = down)
+    pub fn make_move_pose(&mut self, row: f32, col: f32, heading: Option<f32>) {
+        self.inner.move_pacman_pose(row, col, heading);
+    }
+
     pub fn pause(&mut self) {
         self.inner.pause();
     }
@@ -49,7 +123,7 @@ impl PyGameState {
         self.inner.is_paused()
     }
 
-    pub fn get_score(&self) -> u16 {
+    pub fn get_score(&self) -> u32 {
         self.inner.get_score()
     }
 
@@ -61,6 +135,22 @@ impl PyGameState {
         self.inner.get_lives()
     }
 
+    pub fn get_num_pacmen(&self) -> usize {
+        self.inner.num_pacmen()
+    }
+
+    /// Get the position, score and lives of every Pacman as a JSON array
+    pub fn get_pacmen_json(&self) -> String {
+        let pacmen: Vec<_> = (0..self.inner.num_pacmen())
+            .filter_map(|pacman| self.inner.get_pacman_summary(pacman))
+            .collect();
+        serde_json::to_string(&pacmen).unwrap_or_default()
+    }
+
+    pub fn get_bonus_lives_awarded(&self) -> u8 {
+        self.inner.bonus_lives_awarded
+    }
+
     pub fn get_num_pellets(&self) -> u16 {
         self.inner.get_num_pellets()
     }
@@ -81,7 +171,23 @@ impl PyGameState {
         }
     }
 
-    pub fn get_curr_ticks(&self) -> u16 {
+    pub fn get_fruit_pos(&self) -> (i8, i8) {
+        self.inner.fruit_loc.get_coords()
+    }
+
+    pub fn get_fruit_steps(&self) -> u8 {
+        self.inner.get_fruit_steps()
+    }
+
+    pub fn get_fruit_type(&self) -> String {
+        self.inner.get_fruit_type().to_string()
+    }
+
+    pub fn get_fruit_points(&self) -> u16 {
+        self.inner.get_fruit_type().points()
+    }
+
+    pub fn get_curr_ticks(&self) -> u64 {
         self.inner.get_curr_ticks()
     }
 
@@ -89,28 +195,120 @@ impl PyGameState {
         self.inner.serialize()
     }
 
+    /// Serialize the state in the Go server's binary format
+    pub fn serialize_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.inner.serialize_bytes())
+    }
+
+    /// Take all queued game events as a JSON array
+    pub fn drain_events_json(&mut self) -> String {
+        self.inner.drain_events_json()
+    }
+
     pub fn reset(&mut self) {
-        self.inner = GameState::new();
+        self.inner = GameState::with_rules(self.inner.rules.clone());
     }
 
-    pub fn update(&mut self) {
-        if self.inner.update_ready() {
-            self.inner.update_all_ghosts();
-            self.inner.try_respawn_pacman();
-            if self.inner.get_pause_on_update() {
-                self.inner.pause();
-                self.inner.set_pause_on_update(false);
-            }
-            self.inner.check_collisions();
-            self.inner.handle_step_events();
-            self.inner.plan_all_ghosts();
+    /// Replace this state's rules with a GameRules JSON object (fields it
+    /// leaves out take their defaults); Pacman, the ghosts and the pellets
+    /// stay where they are
+    pub fn set_rules_json(&mut self, json: &str) -> PyResult<()> {
+        let rules = GameRules::from_json(json)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
+        self.inner.set_rules(rules);
+        Ok(())
+    }
+
+    /// Use a ghost AI difficulty preset ("easy", "normal", "arcade", "hard"
+    /// or "expert")
+    pub fn set_difficulty(&mut self, difficulty: &str) -> PyResult<()> {
+        let difficulty: Difficulty = difficulty
+            .parse()
+            .map_err(pyo3::exceptions::PyValueError::new_err)?;
+        self.inner.rules.set_difficulty(difficulty);
+        Ok(())
+    }
+
+    pub fn get_rules_json(&self) -> String {
+        serde_json::to_string(&self.inner.rules).unwrap_or_default()
+    }
+
+    /// Pick Pacman's move from this state by expectimax, with settings such
+    /// as {"depth": 6, "time_budget_ms": 20}; returns JSON with the chosen
+    /// move, its value breakdown and the value of every legal move (the GIL
+    /// is released during the search)
+    #[pyo3(signature = (config_json = None))]
+    pub fn search_json(&self, py: Python<'_>, config_json: Option<&str>) -> PyResult<String> {
+        let config = match config_json {
+            Some(json) => SearchConfig::from_json(json)
+                .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?,
+            None => SearchConfig::default(),
+        };
+        let result = py.detach(|| self.inner.search(config));
+        Ok(serde_json::to_string(&result).unwrap_or_default())
+    }
+
+    /// Grow Monte Carlo search trees from this state, with settings such as
+    /// {"threads": 4, "rollout": "random"}; returns JSON with the most visited
+    /// move and each move's visit count and mean value (the GIL is released
+    /// during the search)
+    #[pyo3(signature = (config_json = None))]
+    pub fn mcts_json(&self, py: Python<'_>, config_json: Option<&str>) -> PyResult<String> {
+        let config = match config_json {
+            Some(json) => MctsConfig::from_json(json)
+                .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?,
+            None => MctsConfig::default(),
+        };
+        let result = py.detach(|| self.inner.mcts(config));
+        Ok(serde_json::to_string(&result).unwrap_or_default())
+    }
+
+    pub fn get_pacman_speed(&self) -> u8 {
+        self.inner.pacman_speed()
+    }
+
+    pub fn get_ghost_speed(&self, color: u8) -> u8 {
+        if (color as usize) < crate::constants::NUM_COLORS {
+            self.inner.ghost_speed(color as usize)
+        } else {
+            0
         }
     }
 
+    pub fn update(&mut self) {
+        self.inner.update();
+    }
+
+    /// Run the game loop for a number of ticks, even if paused
+    pub fn step_ticks(&mut self, ticks: u16) {
+        self.inner.step_ticks(ticks);
+    }
+
     pub fn interpret_command(&mut self, msg: Vec<u8>) -> bool {
         interpret_command(&msg, &mut self.inner, &self.logger)
     }
 
+    /// Apply a legacy or framed command, raising ValueError if it is invalid
+    /// or needs the engine's state history (a rewind); returns its status
+    /// ("applied", "ignored_paused", "blocked_by_wall", "buffered" or
+    /// "unconfirmed") and the state JSON if it requested it
+    pub fn execute_command(&mut self, msg: Vec<u8>) -> PyResult<(&'static str, Option<String>)> {
+        let effect = execute_command(&msg, &mut self.inner, &self.logger)
+            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
+        match effect {
+            CommandEffect::Reset => self.reset(),
+            CommandEffect::StepTicks(ticks) => self.inner.step_ticks(ticks),
+            CommandEffect::Rewind(_) => {
+                return Err(pyo3::exceptions::PyValueError::new_err(
+                    "rewinding needs the state history of a game engine",
+                ))
+            }
+            _ => {}
+        }
+        let state = (effect == CommandEffect::SendState).then(|| self.inner.serialize());
+        Ok((ResponseStatus::of(&effect).name(), state))
+    }
+
     pub fn __repr__(&self) -> String {
         format!(
             "PyGameState(score={}, pacman=({}, {}), lives={}, level={}, pellets={})",
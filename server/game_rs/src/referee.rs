@@ -0,0 +1,204 @@
+// referee.rs - Referee corrections for live match adjudication
+
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::constants::*;
+use crate::game_helpers::{get_bit, modify_bit};
+use crate::state::GameState;
+
+/// A referee action, with the wall-clock time and tick it was applied at
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct RefereeLogEntry {
+    pub unix_ms: u64,
+    pub tick: u64,
+    pub action: String,
+}
+
+impl GameState {
+    /*************************** Referee Log ***************************/
+
+    /// Record a referee action in the log
+    pub fn log_referee(&mut self, action: String) {
+        let unix_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        info!("REF: {} (t = {})", action, self.curr_ticks);
+        self.referee_log.push(RefereeLogEntry {
+            unix_ms,
+            tick: self.curr_ticks,
+            action,
+        });
+    }
+
+    /************************* Referee Corrections *************************/
+
+    /// Give Pacman an extra life
+    pub fn grant_life(&mut self) {
+        let lives = self.get_lives().saturating_add(1);
+        self.log_referee(format!("grant life ({} -> {})", self.get_lives(), lives));
+        self.set_lives(lives);
+    }
+
+    /// Take a life away from Pacman (the game pauses if none are left)
+    pub fn remove_life(&mut self) {
+        let lives = self.get_lives().saturating_sub(1);
+        self.log_referee(format!("remove life ({} -> {})", self.get_lives(), lives));
+        self.set_lives(lives);
+        if self.is_game_over() {
+            self.pause();
+        }
+    }
+
+    /// Put Pacman at a cell directly, without pathing or collecting pellets;
+    /// returns false if the cell is a wall
+    pub fn place_pacman(&mut self, row: i8, col: i8) -> bool {
+        if self.wall_at(row, col) {
+            return false;
+        }
+        let (old_row, old_col) = self.pacman_loc.get_coords();
+        self.log_referee(format!(
+            "place pacman ({}, {}) -> ({}, {})",
+            old_row, old_col, row, col
+        ));
+        self.pacman_prev_loc.copy_from(&empty_loc());
+        self.pacman_loc.update_coords(row, col);
+        true
+    }
+
+    /// Put back a pellet that was wrongly collected; returns false if the cell
+    /// never had a pellet
+    pub fn restore_pellet(&mut self, row: i8, col: i8) -> bool {
+        if !self.in_bounds(row, col) || !get_bit(INIT_PELLETS[row as usize], col as u32) {
+            return false;
+        }
+        if self.pellet_at(row, col) {
+            return true;
+        }
+        self.log_referee(format!("restore pellet ({}, {})", row, col));
+        modify_bit(&mut self.pellets[row as usize], col as u32, true);
+        self.num_pellets += 1;
+        true
+    }
+
+    /// Finish the current level, as if the last pellet was collected
+    pub fn end_level(&mut self) {
+        self.log_referee(format!("end level {}", self.get_level()));
+        self.level_reset();
+        self.increment_level();
+    }
+}
+
+/*************************** State History ***************************/
+
+/// Recent snapshots of the game state, for rewinding after a tracking glitch
+#[derive(Clone, Debug, Default)]
+pub struct StateHistory {
+    snapshots: VecDeque<GameState>,
+    max_ticks: u64,
+}
+
+impl StateHistory {
+    /// Keep enough history to rewind REWIND_MAX_SECONDS at the given clock rate
+    pub fn new(clock_rate: u32) -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+            max_ticks: REWIND_MAX_SECONDS as u64 * clock_rate as u64,
+        }
+    }
+
+    /// Forget every snapshot (e.g. on reset)
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+
+    /// Take a snapshot of the state, dropping the ones too old to rewind to
+    pub fn record(&mut self, gs: &GameState) {
+        if self
+            .snapshots
+            .back()
+            .is_some_and(|last| last.curr_ticks == gs.curr_ticks)
+        {
+            self.snapshots.pop_back();
+        }
+
+        let mut snapshot = gs.clone();
+        snapshot.events.clear();
+        snapshot.referee_log.clear();
+        self.snapshots.push_back(snapshot);
+
+        while self
+            .snapshots
+            .front()
+            .is_some_and(|first| first.curr_ticks + self.max_ticks < gs.curr_ticks)
+        {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Take the latest snapshot at least the given number of ticks old,
+    /// discarding everything after it (nothing is discarded if there is no
+    /// such snapshot)
+    pub fn rewind(&mut self, curr_ticks: u64, ticks: u64) -> Option<GameState> {
+        let target = curr_ticks.saturating_sub(ticks);
+        let idx = self
+            .snapshots
+            .iter()
+            .rposition(|snapshot| snapshot.curr_ticks <= target)?;
+        self.snapshots.truncate(idx + 1);
+        self.snapshots.back().cloned()
+    }
+}
+
+impl GameState {
+    /// Go back to an earlier snapshot, keeping the rules, referee log and any
+    /// events not yet drained; the game is paused afterwards
+    pub fn restore_snapshot(&mut self, snapshot: GameState) {
+        let events = std::mem::take(&mut self.events);
+        let referee_log = std::mem::take(&mut self.referee_log);
+        let rules = self.rules.clone();
+
+        *self = snapshot;
+        self.events = events;
+        self.referee_log = referee_log;
+        self.rules = rules;
+        self.pause();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // History with a snapshot every 10 ticks, from tick 50 to 100
+    fn history() -> StateHistory {
+        let mut history = StateHistory::new(24);
+        let mut gs = GameState::new();
+        for tick in (50..=100).step_by(10) {
+            gs.curr_ticks = tick;
+            history.record(&gs);
+        }
+        history
+    }
+
+    #[test]
+    fn rewind_takes_latest_old_enough_snapshot() {
+        let mut history = history();
+        let snapshot = history.rewind(100, 25).unwrap();
+        assert_eq!(snapshot.curr_ticks, 70);
+        assert_eq!(history.snapshots.back().unwrap().curr_ticks, 70);
+    }
+
+    #[test]
+    fn failed_rewind_keeps_history() {
+        let mut history = history();
+        let len = history.snapshots.len();
+        assert!(history.rewind(100, 60).is_none());
+        assert_eq!(history.snapshots.len(), len);
+        assert!(history.rewind(100, 50).is_some());
+    }
+}
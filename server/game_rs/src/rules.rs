@@ -0,0 +1,101 @@
+// rules.rs - Optional rule set, selecting between game model variants
+
+use serde::{Deserialize, Serialize};
+
+use crate::collision::Footprints;
+use crate::constants::*;
+use crate::ghost_ai::{Difficulty, GhostAi};
+use crate::ghost_control::GhostControl;
+use crate::speed::SpeedModel;
+use crate::tracking::{CvFilter, Interpolation, PoseInput};
+
+/// How the ghosts get angrier as the pellets run out
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub enum AngerModel {
+    /// Every ghost speeds up at ANGER_THRESHOLD_1/2 (the Go server's behavior)
+    #[default]
+    Global,
+    /// Only red speeds up ("Cruise Elroy"), at per-level pellet counts
+    Elroy,
+}
+
+/// How ghosts waiting in the ghost house are released
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub enum HouseRelease {
+    /// Fixed GHOST_TRAPPED_STEPS per color (the Go server's behavior)
+    #[default]
+    Timed,
+    /// Arcade personal/global pellet counters, plus a no-pellet timer
+    DotCounter,
+}
+
+/// Extra lives awarded at score thresholds
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct BonusLifeRule {
+    /// Score at which the (first) extra life is awarded
+    pub score: u32,
+    /// Award another life every `score` points, instead of only once
+    pub repeat: bool,
+    /// Lives are never raised above this cap
+    pub max_lives: u8,
+}
+
+impl Default for BonusLifeRule {
+    fn default() -> Self {
+        Self {
+            score: BONUS_LIFE_SCORE,
+            repeat: false,
+            max_lives: MAX_LIVES,
+        }
+    }
+}
+
+/// Game rule options (the defaults match the Go server)
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct GameRules {
+    /// Fractional per-entity speeds; `None` moves every ghost once per update period
+    pub speed_model: Option<SpeedModel>,
+    /// Eaten ghosts travel back to the ghost house as eyes, instead of teleporting
+    pub ghost_eyes: bool,
+    /// Anger model applied when few pellets remain
+    pub anger_model: AngerModel,
+    /// Release rule for ghosts waiting in the ghost house
+    pub house_release: HouseRelease,
+    /// Extra lives at score thresholds; `None` never awards any
+    pub bonus_life: Option<BonusLifeRule>,
+    /// Blocked moves are kept, and taken at the next cell where they are legal
+    pub buffered_turns: bool,
+    /// Pacman keeps moving on its own (at its speed), and moves only turn it
+    pub continuous_motion: bool,
+    /// Filter for CV position updates; `None` trusts every update
+    pub cv_filter: Option<CvFilter>,
+    /// Quantization of sub-cell poses from the CV client
+    pub pose_input: PoseInput,
+    /// Heading-aware path interpolation for absolute moves; `None` walks the
+    /// first shortest path found, up to MAX_INTERPOLATION_LEN cells
+    pub interpolation: Option<Interpolation>,
+    /// Robot footprints, so neighboring robots can collide; `None` only
+    /// collides on the same cell
+    pub footprints: Option<Footprints>,
+    /// Number of Pacmen sharing the board (0 or 1 for the usual single Pacman)
+    pub pacmen: u8,
+    /// Externally controlled ghosts (taken over by a client)
+    pub ghost_control: GhostControl,
+    /// Random turns, lookahead and pack play for the ghosts (see Difficulty);
+    /// `None` keeps the deterministic arcade ghosts
+    pub ghost_ai: Option<GhostAi>,
+}
+
+impl GameRules {
+    /// Parse a rule set from JSON (missing fields keep their defaults)
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Use the ghost AI settings of a difficulty preset
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.ghost_ai = difficulty.ghost_ai();
+    }
+}
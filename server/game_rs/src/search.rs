@@ -0,0 +1,461 @@
+// search.rs - Expectimax search over Pacman's moves
+//
+// Bots plan by simulating the game forward, which is slow in Python. This
+// searches Pacman's move sequences natively, on clones of the real game
+// state, so the ghosts behave exactly as they would in the game: one Pacman
+// move per update period, with the ghosts (and everything else) stepped by
+// the usual game loop. Wherever the ghosts move at random (frightened ghosts,
+// or random turns under the ghost AI settings), the outcome is averaged over
+// several samples of the RNG, making those steps chance nodes.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::*;
+use crate::direction::{Direction, D_COL, D_ROW, NUM_DIRS};
+use crate::state::GameState;
+
+// Weight of each cell between Pacman and the nearest pellet, in points
+const PELLET_DISTANCE_WEIGHT: f64 = 1.0;
+
+// Cells within which a (non-frightened) ghost is a danger, and the weight of
+// the danger, in points, at each cell closer than that
+const GHOST_DANGER_RADIUS: i32 = 3;
+const GHOST_DANGER_WEIGHT: f64 = 20.0;
+
+// Deepest search run without a time budget (deeper ones could run for hours)
+pub const MAX_SEARCH_DEPTH: u8 = 6;
+
+/// Settings for a search
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct SearchConfig {
+    /// Deepest search, in Pacman moves (one move per update period)
+    pub depth: u8,
+    /// Time budget in milliseconds (0 for none, up to MAX_SEARCH_DEPTH;
+    /// deeper searches keep the default budget); searches deepen one move at
+    /// a time while time remains, and the first move is always searched
+    pub time_budget_ms: u64,
+    /// Samples of the ghosts' moves at each chance node
+    pub chance_samples: u8,
+    /// Value of losing a life, in points
+    pub death_penalty: f64,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            depth: 4,
+            time_budget_ms: 50,
+            chance_samples: 4,
+            death_penalty: 1000.0,
+        }
+    }
+}
+
+impl SearchConfig {
+    /// Parse search settings from JSON (missing fields keep their defaults)
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+/// Value of a position, by where it comes from (the value is the total)
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct ValueBreakdown {
+    /// Points scored since the search began
+    pub score: f64,
+    /// Penalty for lives lost (or bonus for lives won) since the search began
+    pub lives: f64,
+    /// Penalty for the distance to the nearest pellet
+    pub pellet_distance: f64,
+    /// Penalty for (non-frightened) ghosts close to Pacman
+    pub ghost_danger: f64,
+}
+
+impl ValueBreakdown {
+    pub fn total(&self) -> f64 {
+        self.score + self.lives + self.pellet_distance + self.ghost_danger
+    }
+
+    // Component-wise average of some breakdowns
+    fn mean(values: &[ValueBreakdown]) -> ValueBreakdown {
+        let n = values.len().max(1) as f64;
+        let sum = values
+            .iter()
+            .fold(ValueBreakdown::default(), |acc, v| ValueBreakdown {
+                score: acc.score + v.score,
+                lives: acc.lives + v.lives,
+                pellet_distance: acc.pellet_distance + v.pellet_distance,
+                ghost_danger: acc.ghost_danger + v.ghost_danger,
+            });
+        ValueBreakdown {
+            score: sum.score / n,
+            lives: sum.lives / n,
+            pellet_distance: sum.pellet_distance / n,
+            ghost_danger: sum.ghost_danger / n,
+        }
+    }
+}
+
+/// Value of one of Pacman's moves
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ActionValue {
+    pub action: Direction,
+    pub value: f64,
+    pub breakdown: ValueBreakdown,
+}
+
+/// Outcome of a search
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct SearchResult {
+    /// Best move (None to stay put, or to keep going with continuous motion)
+    pub action: Direction,
+    pub value: f64,
+    pub breakdown: ValueBreakdown,
+    /// Every move searched, with its value, at the deepest completed depth
+    pub actions: Vec<ActionValue>,
+    /// Deepest search completed within the time budget
+    pub depth: u8,
+    /// Positions simulated
+    pub nodes: u64,
+    pub elapsed_ms: u64,
+}
+
+/// Moves Pacman can make from where he is (staying put included)
+pub fn legal_actions(gs: &GameState) -> Vec<Direction> {
+    let mut actions: Vec<Direction> = (0..NUM_DIRS)
+        .map(Direction::from_index)
+        .filter(|&dir| {
+            let (row, col) = gs.pacman_loc.get_neighbor_coords(dir);
+            !gs.pacman_loc.is_empty() && !gs.wall_at(row, col)
+        })
+        .collect();
+    actions.push(Direction::None);
+    actions
+}
+
+/// Copy of a game state to simulate from (unpaused, with nothing to drain)
+pub fn simulation_root(gs: &GameState) -> GameState {
+    let mut root = gs.clone();
+    root.events.clear();
+    root.referee_log.clear();
+    if root.is_paused() && !root.is_game_over() {
+        root.mode = root.last_unpaused_mode;
+    }
+    root
+}
+
+/// Whether nothing more can happen without the game being resumed (a life
+/// was lost, the level ended, or the game is over)
+pub fn is_terminal(gs: &GameState) -> bool {
+    gs.is_paused() || gs.get_pause_on_update() || gs.is_game_over()
+}
+
+/// Whether the ghosts' next moves depend on the RNG
+pub fn ghosts_random(gs: &GameState) -> bool {
+    let random_turns = gs
+        .rules
+        .ghost_ai
+        .as_ref()
+        .is_some_and(|ai| ai.epsilon > 0.0);
+    random_turns || gs.ghosts.iter().any(|ghost| ghost.is_frightened())
+}
+
+/// Make a move for Pacman, then run the game until the next one is due
+pub fn simulate_step(gs: &mut GameState, action: Direction) {
+    if action != Direction::None {
+        gs.move_pacman_dir(action);
+    }
+    if !is_terminal(gs) {
+        gs.step_ticks(gs.get_update_period() as u16);
+    }
+}
+
+// Run some simulation without logging any of it
+pub(crate) fn silently<T>(f: impl FnOnce() -> T) -> T {
+    tracing::dispatcher::with_default(&tracing::Dispatch::none(), f)
+}
+
+/// Expectimax search from a game state
+pub struct Searcher {
+    config: SearchConfig,
+    root_score: u32,
+    root_lives: u8,
+    deadline: Option<Instant>,
+    nodes: u64,
+    seeds: StdRng,
+}
+
+impl Searcher {
+    pub fn new(config: SearchConfig) -> Self {
+        Self {
+            config,
+            root_score: 0,
+            root_lives: 0,
+            deadline: None,
+            nodes: 0,
+            seeds: StdRng::seed_from_u64(0),
+        }
+    }
+
+    /// Find Pacman's best move, deepening the search until the depth or the
+    /// time budget runs out
+    pub fn search(&mut self, gs: &GameState) -> SearchResult {
+        let start = Instant::now();
+        let root = simulation_root(gs);
+        self.root_score = root.curr_score;
+        self.root_lives = root.curr_lives;
+        self.nodes = 0;
+
+        // Chance nodes draw from the game's RNG, so searches are repeatable
+        self.seeds = StdRng::seed_from_u64(root.rng.clone().gen());
+
+        // A search needs an end: deep searches always have a time budget
+        let budget_ms = if self.config.time_budget_ms == 0 && self.config.depth > MAX_SEARCH_DEPTH {
+            SearchConfig::default().time_budget_ms
+        } else {
+            self.config.time_budget_ms
+        };
+        let budget = Duration::from_millis(budget_ms);
+        let mut actions = Vec::new();
+        let mut depth_reached = 0;
+        silently(|| {
+            for depth in 1..=self.config.depth.max(1) {
+                // The first move is always searched in full
+                self.deadline = (depth > 1 && !budget.is_zero()).then(|| start + budget);
+                match self.root_values(&root, depth) {
+                    Some(values) => {
+                        actions = values;
+                        depth_reached = depth;
+                    }
+                    None => break,
+                }
+                if self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline)
+                {
+                    break;
+                }
+            }
+        });
+
+        // Ties go to the first move listed
+        let best = actions
+            .iter()
+            .fold(None::<&ActionValue>, |best, action| match best {
+                Some(best) if best.value >= action.value => Some(best),
+                _ => Some(action),
+            })
+            .cloned()
+            .unwrap_or(ActionValue {
+                action: Direction::None,
+                value: 0.0,
+                breakdown: ValueBreakdown::default(),
+            });
+        SearchResult {
+            action: best.action,
+            value: best.value,
+            breakdown: best.breakdown,
+            actions,
+            depth: depth_reached,
+            nodes: self.nodes,
+            elapsed_ms: start.elapsed().as_millis() as u64,
+        }
+    }
+
+    // Values of each of Pacman's moves from the root (None if out of time)
+    fn root_values(&mut self, root: &GameState, depth: u8) -> Option<Vec<ActionValue>> {
+        legal_actions(root)
+            .into_iter()
+            .map(|action| {
+                let breakdown = self.chance(root, action, depth)?;
+                Some(ActionValue {
+                    action,
+                    value: breakdown.total(),
+                    breakdown,
+                })
+            })
+            .collect()
+    }
+
+    // Best value Pacman can expect from a position (None if out of time)
+    fn expectimax(&mut self, gs: &GameState, depth: u8) -> Option<ValueBreakdown> {
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return None;
+        }
+        if depth == 0 || is_terminal(gs) {
+            return Some(self.evaluate(gs));
+        }
+        let mut best: Option<ValueBreakdown> = None;
+        for action in legal_actions(gs) {
+            let value = self.chance(gs, action, depth)?;
+            if best.is_none_or(|best| value.total() > best.total()) {
+                best = Some(value);
+            }
+        }
+        best.or_else(|| Some(self.evaluate(gs)))
+    }
+
+    // Expected value of a move, averaged over the ghosts' random moves
+    fn chance(&mut self, gs: &GameState, action: Direction, depth: u8) -> Option<ValueBreakdown> {
+        let mut values = Vec::new();
+        let samples = self.config.chance_samples.max(1);
+        let random = ghosts_random(gs);
+        for sample in 0..samples {
+            // Reseed every sample, as the step itself may frighten the ghosts
+            let mut child = gs.clone();
+            child.rng = StdRng::seed_from_u64(self.seeds.gen());
+            simulate_step(&mut child, action);
+            self.nodes += 1;
+
+            // Ghosts frightened during the move may have moved at random too
+            let random_step = random || ghosts_random(&child);
+            values.push(self.expectimax(&child, depth - 1)?);
+            if sample == 0 && !random_step {
+                break;
+            }
+        }
+        Some(ValueBreakdown::mean(&values))
+    }
+
+    /// Value of a position, relative to where the search began
+    pub fn evaluate(&self, gs: &GameState) -> ValueBreakdown {
+        evaluate(
+            gs,
+            self.root_score,
+            self.root_lives,
+            self.config.death_penalty,
+        )
+    }
+}
+
+/// Value of a position, relative to the score and lives a search began with
+pub fn evaluate(
+    gs: &GameState,
+    root_score: u32,
+    root_lives: u8,
+    death_penalty: f64,
+) -> ValueBreakdown {
+    let lives_lost = root_lives as f64 - gs.curr_lives as f64;
+    let mut value = ValueBreakdown {
+        score: gs.curr_score as f64 - root_score as f64,
+        lives: -lives_lost * death_penalty,
+        ..Default::default()
+    };
+    if gs.pacman_loc.is_empty() {
+        return value;
+    }
+
+    let (row, col) = gs.pacman_loc.get_coords();
+    if let Some(dist) = nearest_pellet_dist(gs, row, col) {
+        value.pellet_distance = -PELLET_DISTANCE_WEIGHT * dist as f64;
+    }
+    for ghost in gs.ghosts.iter() {
+        if ghost.loc.is_empty() || ghost.is_frightened() || ghost.is_eyes() {
+            continue;
+        }
+        let (ghost_row, ghost_col) = ghost.loc.get_coords();
+        let dist = (ghost_row as i32 - row as i32).abs() + (ghost_col as i32 - col as i32).abs();
+        if dist <= GHOST_DANGER_RADIUS {
+            let closeness = (GHOST_DANGER_RADIUS + 1 - dist) as f64;
+            value.ghost_danger -= GHOST_DANGER_WEIGHT * closeness * closeness;
+        }
+    }
+    value
+}
+
+/// Cells Pacman must cross to reach the nearest pellet (None if none are left
+/// within reach)
+pub fn nearest_pellet_dist(gs: &GameState, row: i8, col: i8) -> Option<u16> {
+    let mut seen = [[false; MAZE_COLS as usize]; MAZE_ROWS as usize];
+    let mut queue = VecDeque::from([(row, col, 0u16)]);
+    seen[row as usize][col as usize] = true;
+    while let Some((row, col, dist)) = queue.pop_front() {
+        if gs.pellet_at(row, col) {
+            return Some(dist);
+        }
+        for dir in 0..NUM_DIRS as usize {
+            let (next_row, next_col) = (row + D_ROW[dir], col + D_COL[dir]);
+            if gs.wall_at(next_row, next_col) || seen[next_row as usize][next_col as usize] {
+                continue;
+            }
+            seen[next_row as usize][next_col as usize] = true;
+            queue.push_back((next_row, next_col, dist + 1));
+        }
+    }
+    None
+}
+
+impl GameState {
+    /// Search Pacman's moves from this state (the state itself is unchanged)
+    pub fn search(&self, config: SearchConfig) -> SearchResult {
+        Searcher::new(config).search(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SearchConfig {
+        SearchConfig {
+            depth: 3,
+            time_budget_ms: 0,
+            ..Default::default()
+        }
+    }
+
+    // A game in progress, with the ghosts' RNG seeded
+    fn seeded_game(seed: u64) -> GameState {
+        let mut gs = GameState::new();
+        gs.play();
+        gs.rng = StdRng::seed_from_u64(seed);
+        gs.step_ticks(40);
+        gs
+    }
+
+    #[test]
+    fn search_is_repeatable() {
+        let gs = seeded_game(7);
+        let first = gs.search(config());
+        let second = gs.search(config());
+        assert_eq!(first.action, second.action);
+        assert_eq!(first.actions, second.actions);
+        assert_eq!(first.nodes, second.nodes);
+        assert_eq!(first.depth, 3);
+    }
+
+    #[test]
+    fn search_picks_a_legal_action() {
+        for seed in 0..4 {
+            let gs = seeded_game(seed);
+            let legal = legal_actions(&gs);
+            let result = gs.search(config());
+            assert!(legal.contains(&result.action));
+            assert!(result
+                .actions
+                .iter()
+                .all(|value| legal.contains(&value.action)));
+        }
+    }
+
+    #[test]
+    fn deep_searches_keep_a_time_budget() {
+        let gs = seeded_game(3);
+        let result = gs.search(SearchConfig {
+            depth: u8::MAX,
+            time_budget_ms: 0,
+            ..Default::default()
+        });
+        assert!(result.depth < u8::MAX);
+        assert!(result.elapsed_ms >= SearchConfig::default().time_budget_ms);
+    }
+}
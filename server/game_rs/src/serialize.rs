@@ -0,0 +1,187 @@
+// serialize.rs - Go-compatible binary serialization, ported from Go serialize.go
+//
+// IMPORTANT NOTE: All serializations are encoded in big-endian form
+// (most significant byte, MSB, first)
+//
+// The internal score and tick counters are wider than the Go protocol's, so
+// they are narrowed here: the score saturates at 0xffff, and the tick counter
+// wraps around (clients only use it relative to the update period).
+
+use crate::constants::*;
+use crate::location::LocationState;
+use crate::state::GameState;
+
+// Length of a full serialized game state, in bytes
+pub const SERIALIZED_LEN: usize = 2 + 1 + 1 + 2 + 2 // header
+    + 2 + 1 + 1 + 1 // game info
+    + 4 * NUM_COLORS // ghosts
+    + 2 // pacman
+    + 4 // fruit
+    + 4 * MAZE_ROWS as usize; // pellets
+
+/***************************** Field Serialization ****************************/
+
+// Serialize a location (row and column, with the direction deltas in the top bits)
+fn ser_location(loc: &LocationState, buf: &mut Vec<u8>) {
+    buf.push(((loc.dir.get_drow() as u8) << 6) | loc.row as u8);
+    buf.push(((loc.dir.get_dcol() as u8) << 6) | loc.col as u8);
+}
+
+impl GameState {
+    // Serialize the current number of ticks (2 bytes, wrapping)
+    fn ser_curr_ticks(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&(self.get_curr_ticks() as u16).to_be_bytes());
+    }
+
+    // Serialize the update period (1 byte)
+    fn ser_update_period(&self, buf: &mut Vec<u8>) {
+        buf.push(self.get_update_period());
+    }
+
+    // Serialize the game mode (1 byte)
+    fn ser_game_mode(&self, buf: &mut Vec<u8>) {
+        buf.push(self.get_mode());
+    }
+
+    /*
+    Serialize the number of steps until the mode changes, in addition to the
+    duration of the mode in steps (2 bytes)
+    */
+    fn ser_mode_steps(&self, buf: &mut Vec<u8>) {
+        buf.push(self.get_mode_steps());
+        buf.push(MODE_DURATIONS[self.get_last_unpaused_mode() as usize]);
+    }
+
+    // Serialize number of steps before a speedup penalty starts (2 bytes)
+    fn ser_level_steps(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.get_level_steps().to_be_bytes());
+    }
+
+    // Serialize the current score (2 bytes, saturating)
+    fn ser_curr_score(&self, buf: &mut Vec<u8>) {
+        let score = self.get_score().min(u16::MAX as u32) as u16;
+        buf.extend_from_slice(&score.to_be_bytes());
+    }
+
+    // Serialize the current level, lives and ghost combo (1 byte each)
+    fn ser_game_info(&self, buf: &mut Vec<u8>) {
+        buf.push(self.get_level());
+        buf.push(self.get_lives());
+        buf.push(self.ghost_combo);
+    }
+
+    // Serialize a ghost's information (4 bytes)
+    fn ser_ghost(&self, color: u8, buf: &mut Vec<u8>) {
+        let ghost = &self.ghosts[color as usize];
+
+        // Serialize the location information first
+        ser_location(&ghost.loc, buf);
+
+        // Add a flag at the 7th (highest) bit to indicate spawning
+        let spawn_flag: u8 = if ghost.is_spawning() { 0b1000_0000 } else { 0 };
+        buf.push(ghost.get_fright_steps() | spawn_flag);
+
+        // Add a flag at the 7th (highest) bit to indicate eaten (or eyes)
+        let eaten_flag: u8 = if ghost.is_eaten() || ghost.is_eyes() {
+            0b1000_0000
+        } else {
+            0
+        };
+        buf.push(ghost.trapped_steps | eaten_flag);
+    }
+
+    // Serialize the location of Pacman (2 bytes)
+    fn ser_pacman(&self, buf: &mut Vec<u8>) {
+        ser_location(&self.pacman_loc, buf);
+    }
+
+    // Serialize the location of the fruit (null if it doesn't exist), steps and duration (4 bytes)
+    fn ser_fruit(&self, buf: &mut Vec<u8>) {
+        if self.fruit_exists() {
+            ser_location(&self.fruit_loc, buf);
+        } else {
+            ser_location(&empty_loc(), buf);
+        }
+        buf.push(self.get_fruit_steps());
+        buf.push(self.get_fruit_type().spec().duration);
+    }
+
+    // Serialize the pellets (4 * MAZE_ROWS bytes)
+    fn ser_pellets(&self, buf: &mut Vec<u8>) {
+        for row in self.pellets.iter() {
+            buf.extend_from_slice(&row.to_be_bytes());
+        }
+    }
+
+    /***************************** State Serialization ****************************/
+
+    /// Serialize all the information of the game state, in the Go server's format
+    pub fn serialize_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(SERIALIZED_LEN);
+
+        // Packet header - contains the necessary information to render the ticker
+        self.ser_curr_ticks(&mut buf);
+        self.ser_update_period(&mut buf);
+        self.ser_game_mode(&mut buf);
+        self.ser_mode_steps(&mut buf);
+        self.ser_level_steps(&mut buf);
+
+        // General game state information
+        self.ser_curr_score(&mut buf);
+        self.ser_game_info(&mut buf);
+
+        // Ghosts, in the order (red -> pink -> cyan -> orange)
+        for color in [RED, PINK, CYAN, ORANGE] {
+            self.ser_ghost(color, &mut buf);
+        }
+
+        // Pacman, fruit and pellets
+        self.ser_pacman(&mut buf);
+        self.ser_fruit(&mut buf);
+        self.ser_pellets(&mut buf);
+
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Length of the Go server's serialized state
+    const GO_SERIALIZED_LEN: usize = 159;
+
+    #[test]
+    fn serialized_length_matches_go() {
+        let gs = GameState::new();
+        assert_eq!(SERIALIZED_LEN, GO_SERIALIZED_LEN);
+        assert_eq!(gs.serialize_bytes().len(), GO_SERIALIZED_LEN);
+    }
+
+    #[test]
+    fn ticks_wrap_to_two_bytes() {
+        let mut gs = GameState::new();
+        gs.curr_ticks = 0x1_2345_6789;
+        assert_eq!(gs.serialize_bytes()[0..2], [0x67, 0x89]);
+    }
+
+    #[test]
+    fn score_saturates_at_two_bytes() {
+        let mut gs = GameState::new();
+        gs.curr_score = 0x1234;
+        assert_eq!(gs.serialize_bytes()[8..10], [0x12, 0x34]);
+
+        gs.curr_score = 70_000;
+        assert_eq!(gs.serialize_bytes()[8..10], [0xff, 0xff]);
+    }
+
+    #[test]
+    fn pellets_end_the_state() {
+        let gs = GameState::new();
+        let buf = gs.serialize_bytes();
+        let pellets = &buf[buf.len() - 4 * MAZE_ROWS as usize..];
+        for (row, bytes) in gs.pellets.iter().zip(pellets.chunks(4)) {
+            assert_eq!(bytes, row.to_be_bytes());
+        }
+    }
+}
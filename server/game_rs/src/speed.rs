@@ -0,0 +1,318 @@
+// speed.rs - Fractional speed model for Pacman and the ghosts
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::*;
+use crate::state::GameState;
+
+/// Speed percentages for a band of levels (100 = one cell per update period)
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(from = "SpeedLevelJson")]
+pub struct SpeedLevel {
+    pub first_level: u8,
+    pub pacman: u8,
+    pub pacman_fright: u8,
+    pub ghost: u8,
+    pub ghost_fright: u8,
+    pub ghost_tunnel: u8,
+    pub elroy_1: u8,
+    pub elroy_2: u8,
+}
+
+// A speed band as written in JSON (the Elroy speeds are optional, and
+// default to the usual ghost speed)
+#[derive(Deserialize)]
+struct SpeedLevelJson {
+    first_level: u8,
+    pacman: u8,
+    pacman_fright: u8,
+    ghost: u8,
+    ghost_fright: u8,
+    ghost_tunnel: u8,
+    elroy_1: Option<u8>,
+    elroy_2: Option<u8>,
+}
+
+impl From<SpeedLevelJson> for SpeedLevel {
+    fn from(json: SpeedLevelJson) -> Self {
+        Self {
+            first_level: json.first_level,
+            pacman: json.pacman,
+            pacman_fright: json.pacman_fright,
+            ghost: json.ghost,
+            ghost_fright: json.ghost_fright,
+            ghost_tunnel: json.ghost_tunnel,
+            elroy_1: json.elroy_1.unwrap_or(json.ghost),
+            elroy_2: json.elroy_2.unwrap_or(json.ghost),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct SpeedModel {
+    /// Level bands, sorted by first level
+    pub levels: Vec<SpeedLevel>,
+    /// Speed of an eaten ghost on its way back to the ghost house
+    pub ghost_eaten: u8,
+    /// Cells (row, col) where ghosts slow down to the tunnel speed (the
+    /// Pacbot maze has no wraparound tunnel, so there are none by default)
+    #[serde(default)]
+    pub tunnels: Vec<(i8, i8)>,
+}
+
+impl Default for SpeedModel {
+    fn default() -> Self {
+        Self {
+            levels: SPEED_TABLE
+                .iter()
+                .map(|row| SpeedLevel {
+                    first_level: row[0],
+                    pacman: row[1],
+                    pacman_fright: row[2],
+                    ghost: row[3],
+                    ghost_fright: row[4],
+                    ghost_tunnel: row[5],
+                    elroy_1: row[6],
+                    elroy_2: row[7],
+                })
+                .collect(),
+            ghost_eaten: GHOST_EATEN_SPEED,
+            tunnels: Vec::new(),
+        }
+    }
+}
+
+impl SpeedModel {
+    /// Get the speed band that applies to a level
+    pub fn for_level(&self, level: u8) -> Option<&SpeedLevel> {
+        self.levels
+            .iter()
+            .take_while(|band| band.first_level <= level)
+            .last()
+            .or(self.levels.first())
+    }
+
+    /// Whether ghosts slow down at a cell
+    pub fn tunnel_at(&self, row: i8, col: i8) -> bool {
+        self.tunnels.contains(&(row, col))
+    }
+}
+
+impl GameState {
+    /****************************** Speed Lookups ******************************/
+
+    /// Pacman's current speed percentage (100 if the speed model is disabled)
+    pub fn pacman_speed(&self) -> u8 {
+        let Some(model) = &self.rules.speed_model else {
+            return 100;
+        };
+        let Some(band) = model.for_level(self.curr_level) else {
+            return 100;
+        };
+        if self.ghosts.iter().any(|ghost| ghost.is_frightened()) {
+            band.pacman_fright
+        } else {
+            band.pacman
+        }
+    }
+
+    /// A ghost's current speed percentage (100 if the speed model is disabled)
+    pub fn ghost_speed(&self, idx: usize) -> u8 {
+        let Some(model) = &self.rules.speed_model else {
+            return 100;
+        };
+        let ghost = &self.ghosts[idx];
+        if ghost.is_eaten() || ghost.is_eyes() {
+            return model.ghost_eaten;
+        }
+        let Some(band) = model.for_level(self.curr_level) else {
+            return 100;
+        };
+        let (row, col) = ghost.loc.get_coords();
+        if model.tunnel_at(row, col) {
+            band.ghost_tunnel
+        } else if ghost.is_frightened() {
+            band.ghost_fright
+        } else if ghost.get_elroy() == 2 {
+            band.elroy_2
+        } else if ghost.get_elroy() == 1 {
+            band.elroy_1
+        } else {
+            band.ghost
+        }
+    }
+
+    /***************************** Ghost Stepping *****************************/
+
+    /// Add this tick's speed to a ghost's credit, and report whether the
+    /// ghost has built up enough to move one cell (one full update period)
+    fn ghost_step_ready(&mut self, idx: usize) -> bool {
+        let threshold = 100 * self.get_update_period() as u16;
+        let speed = self.ghost_speed(idx) as u16;
+        let ghost = &mut self.ghosts[idx];
+
+        // At most one move per tick, so don't bank more than one step
+        ghost.speed_credit = (ghost.speed_credit + speed).min(threshold);
+        if ghost.speed_credit < threshold {
+            return false;
+        }
+        ghost.speed_credit -= threshold;
+        true
+    }
+
+    /// Move each ghost whose speed credit allows it this tick (only used by
+    /// the speed model; otherwise ghosts move together in update_all_ghosts)
+    pub fn step_ghosts(&mut self) {
+        if self.rules.speed_model.is_none() {
+            return;
+        }
+
+        // Advance every ghost that is due to move
+        let mut moved = [false; NUM_COLORS];
+        for (idx, did_move) in moved.iter_mut().enumerate() {
+            if self.ghost_step_ready(idx) {
+                self.ghosts[idx].advance();
+                *did_move = true;
+            }
+        }
+
+        if !moved.contains(&true) {
+            return;
+        }
+
+        // Check collisions, then plan the next move of the ghosts that moved
+        self.check_collisions();
+        for (idx, did_move) in moved.iter().enumerate() {
+            if *did_move {
+                self.plan_ghost(idx);
+            }
+        }
+    }
+
+    /***************************** Pacman Stepping *****************************/
+
+    /// Add this tick's speed to Pacman's credit, and report whether Pacman
+    /// has built up enough to move one cell (continuous motion only)
+    pub(crate) fn pacman_step_ready(&mut self) -> bool {
+        let threshold = 100 * self.get_update_period() as u16;
+        let speed = self.pacman_speed() as u16;
+
+        // At most one move per tick, so don't bank more than one step
+        self.pacman_speed_credit = (self.pacman_speed_credit + speed).min(threshold);
+        if self.pacman_speed_credit < threshold {
+            return false;
+        }
+        self.pacman_speed_credit -= threshold;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::GameRules;
+
+    fn speed_game(model: SpeedModel) -> GameState {
+        GameState::with_rules(GameRules {
+            speed_model: Some(model),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn bands_cover_levels_from_their_first() {
+        let model = SpeedModel::default();
+        let band_start = |level| model.for_level(level).unwrap().first_level;
+        assert_eq!(band_start(1), 1);
+        assert_eq!(band_start(2), 2);
+        assert_eq!(band_start(4), 2);
+        assert_eq!(band_start(5), 5);
+        assert_eq!(band_start(20), 5);
+        assert_eq!(band_start(255), 21);
+    }
+
+    #[test]
+    fn speeds_follow_the_band_and_ghost_state() {
+        let mut gs = speed_game(SpeedModel::default());
+        let red = RED as usize;
+        assert_eq!(gs.pacman_speed(), 80);
+        assert_eq!(gs.ghost_speed(red), 75);
+
+        gs.set_level(5);
+        assert_eq!(gs.pacman_speed(), 100);
+        assert_eq!(gs.ghost_speed(red), 95);
+
+        gs.ghosts[red].set_fright_steps(10);
+        assert_eq!(gs.pacman_speed(), 100);
+        assert_eq!(gs.ghost_speed(red), 60);
+
+        gs.ghosts[red].set_fright_steps(0);
+        gs.ghosts[red].set_eaten(true);
+        assert_eq!(gs.ghost_speed(red), GHOST_EATEN_SPEED);
+    }
+
+    #[test]
+    fn ghosts_slow_down_in_tunnels() {
+        let mut gs = speed_game(SpeedModel {
+            tunnels: vec![(14, 1)],
+            ..Default::default()
+        });
+        gs.ghosts[RED as usize].loc.update_coords(14, 1);
+        gs.ghosts[PINK as usize].loc.update_coords(14, 2);
+        assert_eq!(gs.ghost_speed(RED as usize), 40);
+        assert_eq!(gs.ghost_speed(PINK as usize), 75);
+    }
+
+    #[test]
+    fn tunnels_are_optional_in_json() {
+        let model: SpeedModel =
+            serde_json::from_str(r#"{"levels": [], "ghost_eaten": 150}"#).unwrap();
+        assert!(model.tunnels.is_empty());
+
+        let json = r#"{"levels": [], "ghost_eaten": 150, "tunnels": [[14, 0]]}"#;
+        let model: SpeedModel = serde_json::from_str(json).unwrap();
+        assert!(model.tunnel_at(14, 0));
+        assert!(!model.tunnel_at(14, 1));
+    }
+
+    #[test]
+    fn elroy_speeds_default_to_the_ghost_speed() {
+        let json = r#"{"first_level": 1, "pacman": 80, "pacman_fright": 90,
+            "ghost": 75, "ghost_fright": 50, "ghost_tunnel": 40}"#;
+        let band: SpeedLevel = serde_json::from_str(json).unwrap();
+        assert_eq!((band.elroy_1, band.elroy_2), (75, 75));
+
+        let json = r#"{"first_level": 1, "pacman": 80, "pacman_fright": 90,
+            "ghost": 75, "ghost_fright": 50, "ghost_tunnel": 40, "elroy_1": 80}"#;
+        let band: SpeedLevel = serde_json::from_str(json).unwrap();
+        assert_eq!((band.elroy_1, band.elroy_2), (80, 75));
+    }
+
+    #[test]
+    fn elroy_speeds_up_red() {
+        let mut gs = speed_game(SpeedModel::default());
+        let red = RED as usize;
+        gs.ghosts[red].set_elroy(1);
+        assert_eq!(gs.ghost_speed(red), 80);
+        gs.ghosts[red].set_elroy(2);
+        assert_eq!(gs.ghost_speed(red), 85);
+        gs.ghosts[red].set_fright_steps(10);
+        assert_eq!(gs.ghost_speed(red), 50);
+    }
+
+    #[test]
+    fn steps_are_fractional_per_tick() {
+        let mut gs = speed_game(SpeedModel::default());
+        let period = gs.get_update_period() as u32;
+
+        // At 80%, Pacman moves 8 times in 10 update periods
+        let pacman_steps = (0..10 * period).filter(|_| gs.pacman_step_ready()).count();
+        assert_eq!(pacman_steps, 8);
+
+        // At 75%, a ghost moves 3 times in 4 update periods, never twice a tick
+        let ghost_steps = (0..4 * period)
+            .filter(|_| gs.ghost_step_ready(RED as usize))
+            .count();
+        assert_eq!(ghost_steps, 3);
+    }
+}
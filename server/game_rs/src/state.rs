@@ -5,14 +5,24 @@ use rand::SeedableRng;
 use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
 
+use std::collections::VecDeque;
+
+use crate::agents::PacmanAgent;
 use crate::constants::*;
+use crate::events::{EventRecord, GameEvent};
+use crate::fruit::Fruit;
 use crate::ghost_state::GhostState;
+use crate::direction::Direction;
 use crate::location::LocationState;
+use crate::moves::QueuedMove;
+use crate::referee::RefereeLogEntry;
+use crate::rules::{AngerModel, GameRules};
+use crate::tracking::Tracking;
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct GameState {
     // Header
-    pub curr_ticks: u16,
+    pub curr_ticks: u64,
     pub update_period: u8,
     pub mode: u8,
     pub last_unpaused_mode: u8,
@@ -21,23 +31,61 @@ pub struct GameState {
     pub level_steps: u16,
 
     // Game info
-    pub curr_score: u16,
+    pub curr_score: u32,
     pub curr_level: u8,
     pub curr_lives: u8,
+    pub bonus_lives_awarded: u8,
 
     // Entities
     pub pacman_loc: LocationState,
+    #[serde(skip, default = "empty_loc")]
+    pub pacman_prev_loc: LocationState,
     pub fruit_loc: LocationState,
     pub fruit_steps: u8,
+    pub fruit_type: Fruit,
     pub ghosts: [GhostState; NUM_COLORS],
     pub ghost_combo: u8,
 
+    // Other Pacmen (multi-agent games), and which one is acting
+    pub pacmen: Vec<PacmanAgent>,
+    #[serde(skip)]
+    pub curr_pacman: u8,
+
+    // Pacman moves waiting to be applied
+    pub buffered_turn: Direction,
+    #[serde(skip)]
+    pub buffered_turn_cells: u8,
+    #[serde(skip)]
+    pub move_queue: VecDeque<QueuedMove>,
+    #[serde(skip)]
+    pub pacman_speed_credit: u16,
+
+    // CV tracking filter
+    pub tracking: Tracking,
+
+    // Ghost house release (dot counter rules)
+    pub global_dot_counter: u16,
+    pub global_dot_counter_active: bool,
+    pub house_pellet_timer: u16,
+
     // Maze
     pub pellets: [u32; MAZE_ROWS as usize],
     pub num_pellets: u16,
     #[serde(skip, default = "default_walls")]
     pub walls: [u32; MAZE_ROWS as usize],
 
+    // Rules
+    #[serde(skip)]
+    pub rules: GameRules,
+
+    // Events waiting to be drained
+    #[serde(skip)]
+    pub events: VecDeque<EventRecord>,
+
+    // Referee corrections applied so far
+    #[serde(skip)]
+    pub referee_log: Vec<RefereeLogEntry>,
+
     // RNG
     #[serde(skip, default = "default_rng")]
     pub rng: StdRng,
@@ -51,9 +99,19 @@ fn default_rng() -> StdRng {
     StdRng::from_entropy()
 }
 
+impl Default for GameState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl GameState {
     pub fn new() -> Self {
-        Self {
+        Self::with_rules(GameRules::default())
+    }
+
+    pub fn with_rules(rules: GameRules) -> Self {
+        let mut state = Self {
             // Header
             curr_ticks: 0,
             update_period: INIT_UPDATE_PERIOD,
@@ -67,11 +125,14 @@ impl GameState {
             curr_score: 0,
             curr_level: INIT_LEVEL,
             curr_lives: INIT_LIVES,
+            bonus_lives_awarded: 0,
 
             // Entities
             pacman_loc: pacman_spawn_loc(),
+            pacman_prev_loc: empty_loc(),
             fruit_loc: fruit_spawn_loc(),
             fruit_steps: 0,
+            fruit_type: Fruit::Cherry,
             ghosts: [
                 GhostState::new(RED),
                 GhostState::new(PINK),
@@ -80,31 +141,118 @@ impl GameState {
             ],
             ghost_combo: 0,
 
+            // Other Pacmen
+            pacmen: Vec::new(),
+            curr_pacman: 0,
+
+            // Pacman moves
+            buffered_turn: Direction::None,
+            buffered_turn_cells: 0,
+            move_queue: VecDeque::new(),
+            pacman_speed_credit: 0,
+
+            // CV tracking filter
+            tracking: Tracking::default(),
+
+            // Ghost house release
+            global_dot_counter: 0,
+            global_dot_counter_active: false,
+            house_pellet_timer: 0,
+
             // Maze
             pellets: INIT_PELLETS,
             num_pellets: INIT_PELLET_COUNT,
             walls: INIT_WALLS,
 
+            // Rules
+            rules,
+
+            // Events
+            events: VecDeque::new(),
+
+            // Referee log
+            referee_log: Vec::new(),
+
             // RNG
             rng: StdRng::from_entropy(),
+        };
+        state.reset_house_counters(false);
+        state.sync_pacmen();
+        state
+    }
+
+    /// Replace the rule set (adding or removing Pacmen to match, and starting
+    /// the house counters over if the house release rule changes)
+    pub fn set_rules(&mut self, rules: GameRules) {
+        let house_release_changed = rules.house_release != self.rules.house_release;
+        self.rules = rules;
+        if house_release_changed {
+            self.reset_house_counters(false);
         }
+        self.sync_pacmen();
     }
 
     /**************************** Tick Functions ****************************/
 
-    pub fn get_curr_ticks(&self) -> u16 {
+    pub fn get_curr_ticks(&self) -> u64 {
         self.curr_ticks
     }
 
     pub fn next_tick(&mut self) {
-        if self.curr_ticks == 0xffff {
+        self.curr_ticks = self.curr_ticks.saturating_add(1);
+    }
+
+    /// Run one tick of the game loop, without advancing the tick counter
+    pub fn update(&mut self) {
+        // Apply any scheduled moves that are due
+        self.for_each_pacman(|gs| gs.apply_queued_moves());
+
+        if self.update_ready() {
+            self.update_all_ghosts();
+            self.for_each_pacman(|gs| gs.try_respawn_pacman());
+
+            // Pause on update if flagged
+            if self.get_pause_on_update() {
+                self.pause();
+                self.set_pause_on_update(false);
+            }
+
+            self.check_collisions();
+            self.handle_step_events();
+            self.plan_all_ghosts();
+        }
+
+        // Keep Pacman moving (continuous motion only), then move any ghosts
+        // due this tick (speed model only)
+        if !self.is_paused() {
+            self.for_each_pacman(|gs| gs.advance_pacman());
+            self.step_ghosts();
+        }
+    }
+
+    /// Run the game loop for a number of ticks, even if the game is paused
+    /// (stops early if the game pauses itself, e.g. on pause-on-update)
+    pub fn step_ticks(&mut self, ticks: u16) {
+        if self.is_game_over() {
             return;
         }
-        if self.curr_ticks == 0xfffe {
-            self.pause();
-            warn!("GAME: Max tick limit reached");
+        let was_paused = self.is_paused();
+        if was_paused {
+            self.mode = self.last_unpaused_mode;
+        }
+
+        for _ in 0..ticks {
+            self.next_tick();
+            self.update();
+            if self.is_paused() {
+                return;
+            }
+        }
+
+        if was_paused {
+            self.last_unpaused_mode = self.mode;
+            self.mode = PAUSED;
         }
-        self.curr_ticks += 1;
     }
 
     /************************ Update Period Functions ************************/
@@ -125,13 +273,55 @@ impl GameState {
 
     /************************** Score Functions **************************/
 
-    pub fn get_score(&self) -> u16 {
+    pub fn get_score(&self) -> u32 {
         self.curr_score
     }
 
     pub fn increment_score(&mut self, change: u16) {
-        let score = self.curr_score as u32;
-        self.curr_score = (score + change as u32).min(65535) as u16;
+        self.curr_score = self.curr_score.saturating_add(change as u32);
+        self.award_bonus_lives();
+    }
+
+    // Award any extra lives earned by the current score
+    fn award_bonus_lives(&mut self) {
+        let Some(rule) = &self.rules.bonus_life else {
+            return;
+        };
+        if rule.score == 0 {
+            return;
+        }
+        let (threshold, repeat, max_lives) = (rule.score, rule.repeat, rule.max_lives);
+
+        loop {
+            // Only one life is awarded, unless the rule repeats
+            if !repeat && self.bonus_lives_awarded > 0 {
+                return;
+            }
+            let Some(next_score) = threshold.checked_mul(self.bonus_lives_awarded as u32 + 1)
+            else {
+                return;
+            };
+            if self.curr_score < next_score || self.bonus_lives_awarded == u8::MAX {
+                return;
+            }
+            self.bonus_lives_awarded += 1;
+
+            if self.curr_lives >= max_lives {
+                continue;
+            }
+            info!(
+                "GAME: Bonus life awarded at {} points ({} -> {}) (t = {})",
+                next_score,
+                self.curr_lives,
+                self.curr_lives + 1,
+                self.curr_ticks
+            );
+            self.curr_lives += 1;
+            self.emit(GameEvent::BonusLife {
+                lives: self.curr_lives,
+                score: self.curr_score,
+            });
+        }
     }
 
     /************************** Level Functions **************************/
@@ -148,6 +338,7 @@ impl GameState {
             self.curr_ticks
         );
         self.curr_level = level;
+        self.emit(GameEvent::LevelChanged { level });
         let suggested = INIT_UPDATE_PERIOD as i32 - 2 * (level as i32 - 1);
         self.set_update_period(suggested.max(1) as u8);
     }
@@ -164,6 +355,9 @@ impl GameState {
             self.curr_ticks
         );
         self.curr_level += 1;
+        self.emit(GameEvent::LevelChanged {
+            level: self.curr_level,
+        });
         let suggested = INIT_UPDATE_PERIOD as i32 - 2 * level as i32;
         self.set_update_period(suggested.max(1) as u8);
     }
@@ -175,8 +369,14 @@ impl GameState {
     }
 
     pub fn set_lives(&mut self, lives: u8) {
+        let was_game_over = self.is_game_over();
         info!("GAME: Lives changed ({} -> {})", self.curr_lives, lives);
         self.curr_lives = lives;
+        self.emit(GameEvent::LivesSet { lives });
+        if !was_game_over && self.is_game_over() {
+            info!("GAME: Game over (t = {})", self.curr_ticks);
+            self.emit(GameEvent::GameOver);
+        }
     }
 
     pub fn decrement_lives(&mut self) {
@@ -190,6 +390,13 @@ impl GameState {
             self.curr_ticks
         );
         self.curr_lives -= 1;
+        self.emit(GameEvent::LifeLost {
+            lives: self.curr_lives,
+        });
+        if self.is_game_over() {
+            info!("GAME: Game over (t = {})", self.curr_ticks);
+            self.emit(GameEvent::GameOver);
+        }
     }
 
     /************************** Pellet Functions **************************/
@@ -219,6 +426,10 @@ impl GameState {
         self.fruit_steps > 0
     }
 
+    pub fn get_fruit_type(&self) -> Fruit {
+        self.fruit_type
+    }
+
     pub fn set_fruit_steps(&mut self, steps: u8) {
         self.fruit_steps = steps;
     }
@@ -288,16 +499,25 @@ impl GameState {
             let new_period = (self.get_update_period() as i32 - 2).max(1) as u8;
             self.set_update_period(new_period);
             self.set_level_steps(LEVEL_PENALTY_DURATION);
+            self.emit(GameEvent::LongGamePenalty {
+                update_period: new_period,
+            });
         }
 
         // Decrement mode steps (only if ghosts aren't angry)
-        if self.get_num_pellets() >= ANGER_THRESHOLD_1 {
+        if self.rules.anger_model == AngerModel::Elroy
+            || self.get_num_pellets() >= ANGER_THRESHOLD_1
+        {
             self.decrement_mode_steps();
         }
 
         // Decrement level steps and fruit steps
         self.decrement_level_steps();
         self.decrement_fruit_steps();
+
+        // Release any ghosts held by the dot counter rules
+        self.inc_house_pellet_timer();
+        self.update_house_release();
     }
 
     /************************ Serialization ************************/
@@ -306,3 +526,65 @@ impl GameState {
         serde_json::to_string(self).unwrap_or_default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::BonusLifeRule;
+
+    fn bonus_life_game(repeat: bool) -> GameState {
+        GameState::with_rules(GameRules {
+            bonus_life: Some(BonusLifeRule {
+                repeat,
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    }
+
+    fn bonus_lives_emitted(gs: &GameState) -> usize {
+        gs.events
+            .iter()
+            .filter(|record| matches!(record.event, GameEvent::BonusLife { .. }))
+            .count()
+    }
+
+    #[test]
+    fn no_bonus_life_without_the_rule() {
+        let mut gs = GameState::new();
+        gs.increment_score(20000);
+        assert_eq!(gs.curr_lives, INIT_LIVES);
+    }
+
+    #[test]
+    fn one_bonus_life_at_the_threshold() {
+        let mut gs = bonus_life_game(false);
+        gs.increment_score(9990);
+        assert_eq!(gs.curr_lives, INIT_LIVES);
+        gs.increment_score(10);
+        assert_eq!(gs.curr_lives, INIT_LIVES + 1);
+
+        // Without repeats, later thresholds award nothing
+        gs.increment_score(30000);
+        assert_eq!(gs.curr_lives, INIT_LIVES + 1);
+        assert_eq!(bonus_lives_emitted(&gs), 1);
+    }
+
+    #[test]
+    fn repeated_bonus_lives_catch_up_and_stop_at_the_cap() {
+        let mut gs = bonus_life_game(true);
+        gs.increment_score(25000);
+        assert_eq!(gs.curr_lives, INIT_LIVES + 2);
+        assert_eq!(bonus_lives_emitted(&gs), 2);
+
+        // Thresholds passed at the cap are used up, not saved for later
+        gs.increment_score(20000);
+        assert_eq!(gs.curr_lives, MAX_LIVES);
+        assert_eq!(gs.bonus_lives_awarded, 4);
+        gs.decrement_lives();
+        gs.increment_score(4000);
+        assert_eq!(gs.curr_lives, MAX_LIVES - 1);
+        gs.increment_score(1000);
+        assert_eq!(gs.curr_lives, MAX_LIVES);
+    }
+}
@@ -0,0 +1,322 @@
+// stats.rs - Per-match statistics, collected from the game events
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::*;
+use crate::events::{EventRecord, GameEvent};
+
+/// Time spent on one level
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct LevelTime {
+    pub level: u8,
+    pub seconds: f64,
+}
+
+/// Where and when Pacman was caught
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Death {
+    pub tick: u64,
+    pub ghost: String,
+    pub row: i8,
+    pub col: i8,
+}
+
+/// Summary of a match, for comparing bot versions
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct MatchReport {
+    pub ended: bool,
+    pub active_seconds: f64,
+    pub paused_seconds: f64,
+    pub pellets: u32,
+    pub super_pellets: u32,
+    pub pellets_per_second: f64,
+    pub levels: Vec<LevelTime>,
+    pub ghosts_per_super_pellet: Vec<u8>,
+    pub mean_ghosts_per_super_pellet: f64,
+    /// How many times the 1st, 2nd, 3rd and 4th ghost of a combo was eaten
+    pub combo_distribution: [u32; NUM_COLORS],
+    pub deaths_by_ghost: BTreeMap<String, u32>,
+    pub deaths: Vec<Death>,
+    pub cv_jumps: u32,
+}
+
+/// Collects statistics over a match, from the events and frames it is fed
+#[derive(Clone, Debug)]
+pub struct MatchStats {
+    clock_rate: u32,
+    last_tick: u64,
+    paused_frames: u64,
+
+    // Levels
+    level: u8,
+    level_start_tick: u64,
+    levels: Vec<LevelTime>,
+
+    // Pellets and ghosts
+    pellets: u32,
+    super_pellets: u32,
+    ghosts_per_super_pellet: Vec<u8>,
+    combo_distribution: [u32; NUM_COLORS],
+
+    // Deaths and tracking
+    deaths: Vec<Death>,
+    cv_jumps: u32,
+    ended: bool,
+}
+
+impl MatchStats {
+    pub fn new(clock_rate: u32) -> Self {
+        Self {
+            clock_rate: clock_rate.max(1),
+            last_tick: 0,
+            paused_frames: 0,
+            level: INIT_LEVEL,
+            level_start_tick: 0,
+            levels: Vec::new(),
+            pellets: 0,
+            super_pellets: 0,
+            ghosts_per_super_pellet: Vec::new(),
+            combo_distribution: [0; NUM_COLORS],
+            deaths: Vec::new(),
+            cv_jumps: 0,
+            ended: false,
+        }
+    }
+
+    // Convert a number of ticks (or frames) to seconds
+    fn seconds(&self, ticks: u64) -> f64 {
+        ticks as f64 / self.clock_rate as f64
+    }
+
+    /// Whether Pacman has run out of lives
+    pub fn is_ended(&self) -> bool {
+        self.ended
+    }
+
+    /// Count one frame of the game loop, at the given tick
+    pub fn record_frame(&mut self, tick: u64, paused: bool) {
+        self.last_tick = self.last_tick.max(tick);
+        if paused {
+            self.paused_frames += 1;
+        }
+    }
+
+    /// Update the statistics from an event, returning true if it ended the match
+    pub fn record(&mut self, record: &EventRecord) -> bool {
+        self.last_tick = self.last_tick.max(record.tick);
+
+        match record.event {
+            GameEvent::PelletEaten { .. } => self.pellets += 1,
+            GameEvent::SuperPelletEaten { .. } => {
+                self.pellets += 1;
+                self.super_pellets += 1;
+                self.ghosts_per_super_pellet.push(0);
+            }
+            GameEvent::GhostEaten { combo, .. } => {
+                if let Some(count) = self.ghosts_per_super_pellet.last_mut() {
+                    *count = count.saturating_add(1);
+                }
+                let idx = (combo.max(1) as usize - 1).min(NUM_COLORS - 1);
+                self.combo_distribution[idx] += 1;
+            }
+            GameEvent::LevelChanged { level } => {
+                self.levels.push(LevelTime {
+                    level: self.level,
+                    seconds: self.seconds(record.tick.saturating_sub(self.level_start_tick)),
+                });
+                self.level = level;
+                self.level_start_tick = record.tick;
+            }
+            GameEvent::PacmanCaught { ghost, row, col } => {
+                self.deaths.push(Death {
+                    tick: record.tick,
+                    ghost: GHOST_NAMES[ghost as usize % NUM_COLORS].to_string(),
+                    row,
+                    col,
+                });
+            }
+            GameEvent::PacmanJumped { .. } => self.cv_jumps += 1,
+            GameEvent::GameOver if !self.ended => {
+                self.ended = true;
+                return true;
+            }
+            GameEvent::LivesSet { lives } | GameEvent::BonusLife { lives, .. } if lives > 0 => {
+                self.ended = false;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Summarize the match so far
+    pub fn report(&self) -> MatchReport {
+        let active_seconds = self.seconds(self.last_tick);
+
+        // Include the time spent on the current level
+        let mut levels = self.levels.clone();
+        levels.push(LevelTime {
+            level: self.level,
+            seconds: self.seconds(self.last_tick.saturating_sub(self.level_start_tick)),
+        });
+
+        let mut deaths_by_ghost: BTreeMap<String, u32> = GHOST_NAMES
+            .iter()
+            .map(|name| (name.to_string(), 0))
+            .collect();
+        for death in self.deaths.iter() {
+            *deaths_by_ghost.entry(death.ghost.clone()).or_default() += 1;
+        }
+
+        let ghosts_eaten: u32 = self.ghosts_per_super_pellet.iter().map(|&n| n as u32).sum();
+
+        MatchReport {
+            ended: self.ended,
+            active_seconds,
+            paused_seconds: self.seconds(self.paused_frames),
+            pellets: self.pellets,
+            super_pellets: self.super_pellets,
+            pellets_per_second: if active_seconds > 0.0 {
+                self.pellets as f64 / active_seconds
+            } else {
+                0.0
+            },
+            levels,
+            ghosts_per_super_pellet: self.ghosts_per_super_pellet.clone(),
+            mean_ghosts_per_super_pellet: if self.super_pellets > 0 {
+                ghosts_eaten as f64 / self.super_pellets as f64
+            } else {
+                0.0
+            },
+            combo_distribution: self.combo_distribution,
+            deaths_by_ghost,
+            deaths: self.deaths.clone(),
+            cv_jumps: self.cv_jumps,
+        }
+    }
+
+    /// Summarize the match so far as JSON
+    pub fn report_json(&self) -> String {
+        serde_json::to_string(&self.report()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::GameState;
+
+    fn record(tick: u64, event: GameEvent) -> EventRecord {
+        EventRecord {
+            tick,
+            pacman: 0,
+            event,
+        }
+    }
+
+    // Feed the game's events to the statistics, counting the match endings
+    fn feed(stats: &mut MatchStats, gs: &mut GameState) -> usize {
+        gs.drain_events()
+            .iter()
+            .filter(|record| stats.record(record))
+            .count()
+    }
+
+    #[test]
+    fn referee_can_end_and_reopen_a_match() {
+        let mut gs = GameState::new();
+        let mut stats = MatchStats::new(24);
+        for _ in 0..INIT_LIVES - 1 {
+            gs.remove_life();
+        }
+        assert_eq!(feed(&mut stats, &mut gs), 0);
+        assert!(!stats.is_ended());
+
+        gs.remove_life();
+        assert_eq!(feed(&mut stats, &mut gs), 1);
+        assert!(stats.is_ended() && stats.report().ended);
+
+        // A life granted after the end reopens the match, until it is lost
+        gs.grant_life();
+        assert_eq!(feed(&mut stats, &mut gs), 0);
+        assert!(!stats.is_ended());
+        gs.remove_life();
+        assert_eq!(feed(&mut stats, &mut gs), 1);
+        assert!(stats.is_ended());
+    }
+
+    #[test]
+    fn game_over_ends_the_match_once() {
+        let mut stats = MatchStats::new(24);
+        assert!(stats.record(&record(10, GameEvent::GameOver)));
+        assert!(!stats.record(&record(11, GameEvent::GameOver)));
+        assert!(!stats.record(&record(12, GameEvent::LivesSet { lives: 0 })));
+        assert!(stats.is_ended());
+    }
+
+    #[test]
+    fn report_summarizes_the_events() {
+        let mut stats = MatchStats::new(24);
+        let events = [
+            (10, GameEvent::PelletEaten { row: 1, col: 1 }),
+            (20, GameEvent::SuperPelletEaten { row: 3, col: 1 }),
+            (
+                30,
+                GameEvent::GhostEaten {
+                    ghost: RED,
+                    points: 200,
+                    combo: 1,
+                },
+            ),
+            (
+                40,
+                GameEvent::GhostEaten {
+                    ghost: PINK,
+                    points: 400,
+                    combo: 2,
+                },
+            ),
+            (
+                120,
+                GameEvent::PacmanCaught {
+                    ghost: CYAN,
+                    row: 23,
+                    col: 13,
+                },
+            ),
+            (240, GameEvent::LevelChanged { level: 2 }),
+            (250, GameEvent::PelletEaten { row: 1, col: 2 }),
+        ];
+        for (tick, event) in events {
+            stats.record(&record(tick, event));
+        }
+        stats.record_frame(288, true);
+
+        let report = stats.report();
+        assert!(!report.ended);
+        assert_eq!(report.pellets, 3);
+        assert_eq!(report.super_pellets, 1);
+        assert_eq!(report.ghosts_per_super_pellet, [2]);
+        assert_eq!(report.mean_ghosts_per_super_pellet, 2.0);
+        assert_eq!(report.combo_distribution, [1, 1, 0, 0]);
+        assert_eq!(report.active_seconds, 12.0);
+        assert_eq!(report.paused_seconds, 1.0 / 24.0);
+        assert_eq!(
+            report.levels,
+            [
+                LevelTime {
+                    level: 1,
+                    seconds: 10.0
+                },
+                LevelTime {
+                    level: 2,
+                    seconds: 2.0
+                },
+            ]
+        );
+        assert_eq!(report.deaths.len(), 1);
+        assert_eq!(report.deaths_by_ghost[GHOST_NAMES[CYAN as usize]], 1);
+        assert_eq!(report.deaths_by_ghost[GHOST_NAMES[RED as usize]], 0);
+    }
+}
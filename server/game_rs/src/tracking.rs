@@ -0,0 +1,361 @@
+// tracking.rs - Filtering of CV position updates (absolute moves)
+//
+// An overhead camera occasionally reports Pacman far from where it really is.
+// With the filter enabled, a position that Pacman couldn't have reached since
+// the last update is only accepted once several updates in a row agree on it.
+//
+// The CV client may also send a sub-cell pose (fractional position and
+// heading), which is quantized to a cell with some hysteresis, so that a robot
+// sitting on a cell boundary doesn't flicker between the two cells.
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::constants::MAX_INTERPOLATION_LEN;
+use crate::direction::Direction;
+use crate::events::GameEvent;
+use crate::game_helpers::{MoveOutcome, Pos};
+use crate::state::GameState;
+
+// Tracking confidence bounds, and how much each kind of update changes it
+pub const MAX_CONFIDENCE: u8 = 100;
+const CONFIDENCE_GAIN: u8 = 10;
+const CONFIDENCE_UNCONFIRMED_LOSS: u8 = 15;
+const CONFIDENCE_INVALID_LOSS: u8 = 25;
+const CONFIDENCE_AFTER_JUMP: u8 = 50;
+
+/// Settings of the CV tracking filter
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct CvFilter {
+    /// Longest path (in cells) Pacman can plausibly cover between two updates
+    pub max_jump: u8,
+    /// Consecutive updates needed to accept a longer jump
+    pub confirm_frames: u8,
+}
+
+impl Default for CvFilter {
+    fn default() -> Self {
+        Self {
+            max_jump: 4,
+            confirm_frames: 3,
+        }
+    }
+}
+
+/// Settings for walking absolute moves along the most plausible path
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct Interpolation {
+    /// Longest path (in cells) that is walked; longer moves jump straight there
+    pub max_len: u8,
+}
+
+impl Default for Interpolation {
+    fn default() -> Self {
+        Self {
+            max_len: MAX_INTERPOLATION_LEN as u8,
+        }
+    }
+}
+
+/// Settings for quantizing sub-cell poses from the CV client
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct PoseInput {
+    /// How far past a cell boundary (in cells) the robot must be to change cell
+    pub hysteresis: f32,
+}
+
+impl Default for PoseInput {
+    fn default() -> Self {
+        Self { hysteresis: 0.2 }
+    }
+}
+
+/// Nearest direction to a heading in degrees (0 = right, 90 = down)
+pub fn heading_to_dir(heading: f32) -> Direction {
+    match (heading.rem_euclid(360.0) / 90.0).round() as u8 % 4 {
+        0 => Direction::Right,
+        1 => Direction::Down,
+        2 => Direction::Left,
+        _ => Direction::Up,
+    }
+}
+
+/// State of the CV tracking filter
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Tracking {
+    /// How much the recent position updates can be trusted (0 - 100)
+    pub confidence: u8,
+    /// Far-away position waiting for confirmation, and how many updates agreed
+    pub candidate: Option<(i8, i8)>,
+    pub candidate_frames: u8,
+    /// Last sub-cell pose reported (row, col), if any
+    #[serde(skip)]
+    pub pose: Option<(f32, f32)>,
+}
+
+impl Default for Tracking {
+    fn default() -> Self {
+        Self {
+            confidence: MAX_CONFIDENCE,
+            candidate: None,
+            candidate_frames: 0,
+            pose: None,
+        }
+    }
+}
+
+impl Tracking {
+    fn gain(&mut self, amount: u8) {
+        self.confidence = self.confidence.saturating_add(amount).min(MAX_CONFIDENCE);
+    }
+
+    fn lose(&mut self, amount: u8) {
+        self.confidence = self.confidence.saturating_sub(amount);
+    }
+}
+
+impl GameState {
+    /// How much the recent CV updates can be trusted (0 - 100)
+    pub fn get_tracking_confidence(&self) -> u8 {
+        self.tracking.confidence
+    }
+
+    /// Note a CV update that can't be right (in a wall, or unreachable)
+    pub(crate) fn reject_tracking_update(&mut self) {
+        if self.rules.cv_filter.is_some() {
+            self.tracking.lose(CONFIDENCE_INVALID_LOSS);
+        }
+    }
+
+    /*
+    Filter a CV update with a known path from Pacman's position: short paths
+    are walked right away, while longer ones wait for confirmation
+    */
+    pub(crate) fn track_pacman(&mut self, row: i8, col: i8, path: Vec<Pos>) -> MoveOutcome {
+        let filter = self.rules.cv_filter.clone().unwrap_or_default();
+
+        // Plausible move, so walk the path (collecting pellets on the way)
+        if path.len() <= filter.max_jump as usize {
+            self.tracking.candidate = None;
+            self.tracking.candidate_frames = 0;
+            self.tracking.gain(CONFIDENCE_GAIN);
+            return self.walk_pacman_path(path);
+        }
+
+        // Count how many updates in a row reported this far-away position
+        if self.tracking.candidate == Some((row, col)) {
+            self.tracking.candidate_frames = self.tracking.candidate_frames.saturating_add(1);
+        } else {
+            self.tracking.candidate = Some((row, col));
+            self.tracking.candidate_frames = 1;
+        }
+        if self.tracking.candidate_frames < filter.confirm_frames {
+            self.tracking.lose(CONFIDENCE_UNCONFIRMED_LOSS);
+            return MoveOutcome::Unconfirmed;
+        }
+
+        // Confirmed, so Pacman really got there
+        warn!(
+            "WARN: Confirmed CV jump of {} cells (t = {})",
+            path.len(),
+            self.curr_ticks
+        );
+        let (from_row, from_col) = self.pacman_loc.get_coords();
+        self.emit(GameEvent::PacmanJumped {
+            from_row,
+            from_col,
+            row,
+            col,
+            path_len: path.len() as u16,
+        });
+        self.tracking.candidate = None;
+        self.tracking.candidate_frames = 0;
+        self.tracking.confidence = self.tracking.confidence.min(CONFIDENCE_AFTER_JUMP);
+
+        // Too far to trust the path in between, as without the filter
+        if path.len() > self.max_interpolation_len() {
+            return self.teleport_pacman(row, col);
+        }
+        self.walk_pacman_path(path)
+    }
+
+    /*
+    Quantize a sub-cell pose (cell centers are at whole numbers) to a cell;
+    along each axis, Pacman only leaves its current cell once the robot is
+    past the cell boundary by the hysteresis margin
+    */
+    pub fn quantize_pose(&self, row: f32, col: f32) -> (i8, i8) {
+        if self.pacman_loc.is_empty() {
+            return (row.round() as i8, col.round() as i8);
+        }
+
+        let limit = 0.5 + self.rules.pose_input.hysteresis.max(0.0);
+        let quantize = |pos: f32, curr: i8| {
+            if (pos - curr as f32).abs() > limit {
+                pos.round() as i8
+            } else {
+                curr
+            }
+        };
+        let (curr_row, curr_col) = self.pacman_loc.get_coords();
+        (quantize(row, curr_row), quantize(col, curr_col))
+    }
+
+    /// Move Pacman from a sub-cell pose (CV update), facing the heading
+    /// (in degrees, 0 = right, 90 = down) if one is given
+    pub fn move_pacman_pose(&mut self, row: f32, col: f32, heading: Option<f32>) -> MoveOutcome {
+        if self.is_paused() || self.get_pause_on_update() {
+            return MoveOutcome::Paused;
+        }
+
+        let (new_row, new_col) = self.quantize_pose(row, col);
+        self.tracking.pose = Some((row, col));
+        let outcome = self.move_pacman_absolute(new_row, new_col);
+
+        // The robot may have moved within its cell, closer to a ghost
+        if self.rules.footprints.is_some() {
+            self.check_pacman_collisions();
+        }
+
+        // Face the robot's real heading, once Pacman is where the pose says
+        if let Some(heading) = heading {
+            if outcome == MoveOutcome::Moved && !self.pacman_loc.is_empty() {
+                self.pacman_loc.update_dir(heading_to_dir(heading));
+            }
+        }
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::GameRules;
+
+    #[test]
+    fn confirmed_long_jump_skips_the_path() {
+        let mut gs = GameState::with_rules(GameRules {
+            cv_filter: Some(CvFilter::default()),
+            ..Default::default()
+        });
+        gs.play();
+        let pellets = gs.num_pellets;
+
+        // (5, 1) is much further from the spawn than the interpolation limit
+        let path = gs.find_likely_path(5, 1).unwrap();
+        assert!(path.len() > gs.max_interpolation_len());
+        for _ in 1..CvFilter::default().confirm_frames {
+            assert_eq!(gs.move_pacman_absolute(5, 1), MoveOutcome::Unconfirmed);
+        }
+        assert_eq!(gs.move_pacman_absolute(5, 1), MoveOutcome::Moved);
+        assert_eq!(gs.pacman_loc.get_coords(), (5, 1));
+        assert_eq!(gs.num_pellets, pellets - 1);
+    }
+
+    #[test]
+    fn headings_round_to_the_nearest_direction() {
+        assert_eq!(heading_to_dir(0.0), Direction::Right);
+        assert_eq!(heading_to_dir(44.0), Direction::Right);
+        assert_eq!(heading_to_dir(46.0), Direction::Down);
+        assert_eq!(heading_to_dir(180.0), Direction::Left);
+        assert_eq!(heading_to_dir(-90.0), Direction::Up);
+        assert_eq!(heading_to_dir(350.0), Direction::Right);
+    }
+
+    #[test]
+    fn poses_change_cell_past_the_hysteresis() {
+        let mut gs = GameState::new();
+        gs.play();
+
+        // With the default margin, Pacman changes cell 0.7 cells from the center
+        assert_eq!(gs.quantize_pose(23.0, 13.6), (23, 13));
+        assert_eq!(gs.quantize_pose(22.4, 13.0), (23, 13));
+        assert_eq!(gs.quantize_pose(23.0, 13.8), (23, 14));
+
+        // Moving back takes as much
+        gs.move_pacman_pose(23.0, 13.8, None);
+        assert_eq!(gs.pacman_loc.get_coords(), (23, 14));
+        assert_eq!(gs.quantize_pose(23.0, 13.4), (23, 14));
+        assert_eq!(gs.quantize_pose(23.0, 13.2), (23, 13));
+
+        // Without a margin, poses round to the nearest cell
+        gs.rules.pose_input.hysteresis = 0.0;
+        assert_eq!(gs.quantize_pose(23.0, 13.4), (23, 13));
+    }
+
+    #[test]
+    fn poses_move_and_turn_pacman() {
+        let mut gs = GameState::new();
+        assert_eq!(
+            gs.move_pacman_pose(23.0, 12.0, Some(180.0)),
+            MoveOutcome::Paused
+        );
+
+        gs.play();
+        assert_eq!(
+            gs.move_pacman_pose(23.1, 11.9, Some(170.0)),
+            MoveOutcome::Moved
+        );
+        assert_eq!(gs.pacman_loc.get_coords(), (23, 12));
+        assert_eq!(gs.pacman_loc.dir, Direction::Left);
+        assert_eq!(gs.tracking.pose, Some((23.1, 11.9)));
+    }
+
+    #[test]
+    fn absolute_moves_collect_pellets_along_the_path() {
+        let mut gs = GameState::new();
+        gs.play();
+        assert!((9..13).all(|col| gs.pellet_at(23, col)));
+        let pellets = gs.num_pellets;
+
+        assert_eq!(gs.move_pacman_absolute(23, 9), MoveOutcome::Moved);
+        assert_eq!(gs.pacman_loc.get_coords(), (23, 9));
+        assert_eq!(gs.pacman_loc.dir, Direction::Left);
+        assert!((9..13).all(|col| !gs.pellet_at(23, col)));
+        assert_eq!(gs.num_pellets, pellets - 4);
+    }
+
+    #[test]
+    fn moves_past_the_interpolation_length_jump() {
+        let mut gs = GameState::with_rules(GameRules {
+            interpolation: Some(Interpolation { max_len: 2 }),
+            ..Default::default()
+        });
+        gs.play();
+        let pellets = gs.num_pellets;
+
+        // Only the pellet where Pacman lands is eaten
+        assert_eq!(gs.move_pacman_absolute(23, 9), MoveOutcome::Moved);
+        assert_eq!(gs.pacman_loc.get_coords(), (23, 9));
+        assert!((10..13).all(|col| gs.pellet_at(23, col)));
+        assert_eq!(gs.num_pellets, pellets - 1);
+        assert!(gs
+            .drain_events()
+            .iter()
+            .any(|record| matches!(record.event, GameEvent::PacmanJumped { path_len: 4, .. })));
+    }
+
+    #[test]
+    fn interpolated_paths_keep_the_heading() {
+        // From (20, 15), (23, 21) is 9 cells away both right-then-down and
+        // down-then-right
+        let mut gs = GameState::with_rules(GameRules {
+            interpolation: Some(Interpolation::default()),
+            ..Default::default()
+        });
+        gs.pacman_loc.update_coords(20, 15);
+
+        gs.pacman_loc.update_dir(Direction::Right);
+        let path = gs.find_likely_path(23, 21).unwrap();
+        assert_eq!(path.len(), 9);
+        assert_eq!(path[0], Pos { r: 20, c: 16 });
+
+        gs.pacman_loc.update_dir(Direction::Down);
+        let path = gs.find_likely_path(23, 21).unwrap();
+        assert_eq!(path.len(), 9);
+        assert_eq!(path[0], Pos { r: 21, c: 15 });
+    }
+}